@@ -0,0 +1,178 @@
+/*
+ * Copyright (c) 2024 Dominik Röttsches
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Compares the throughput of the state machine [`FrameParser`] against the previous
+//! regex-based implementation, and of decoding owned against borrowed frames, replaying the
+//! capture in `dump/dumped_btatt_values.log`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use laad::{decoder::Decoder, frameparser::FrameParser};
+use regex::bytes::Regex;
+
+const REPLAY_ROUNDS: usize = 10;
+
+/// The regex-based frame scanner that `FrameParser` used before, kept here as the baseline.
+/// It compiles a regex for every chunk and rescans all buffered bytes.
+struct RegexFrameParser {
+    buffered_bytes: Vec<u8>,
+}
+
+impl RegexFrameParser {
+    fn de_bytestuff(bytes: &[u8]) -> Vec<u8> {
+        if !bytes.contains(&0xA9) {
+            return bytes.to_vec();
+        }
+        let mut de_bytestuffed = Vec::new();
+        let mut iter = bytes.iter();
+        while let Some(byte) = iter.next() {
+            if *byte == 0xA9 {
+                if let Some(next_byte) = iter.next() {
+                    de_bytestuffed.push(next_byte ^ 0x20);
+                }
+            } else {
+                de_bytestuffed.push(*byte);
+            }
+        }
+        de_bytestuffed
+    }
+
+    fn parse_chunk(&mut self, bytes: &[u8], frames: &mut Vec<Box<[u8]>>) {
+        self.buffered_bytes.extend_from_slice(bytes);
+        let re = Regex::new(r"(?s-u)\xAA(.*?)\x99").unwrap();
+        let mut last_match_end = 0;
+        for cap in re.captures_iter(self.buffered_bytes.as_slice()) {
+            if let Some(matched) = cap.get(0) {
+                last_match_end = matched.end();
+                frames.push(Self::de_bytestuff(matched.as_bytes()).into_boxed_slice());
+            }
+        }
+        self.buffered_bytes.drain(..last_match_end);
+        if self.buffered_bytes.len() > 1024 {
+            if let Some(pos) = self.buffered_bytes.iter().position(|&x| x == 0xAA) {
+                self.buffered_bytes.drain(..pos);
+            } else {
+                self.buffered_bytes.clear();
+            }
+        }
+    }
+}
+
+fn load_chunks() -> Vec<Vec<u8>> {
+    let dump = include_str!("../dump/dumped_btatt_values.log");
+    let chunks: Vec<Vec<u8>> = dump
+        .lines()
+        .map(|line| {
+            (0..line.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&line[i..i + 2], 16).expect("Failed to decode hex"))
+                .collect()
+        })
+        .collect();
+    chunks
+        .iter()
+        .cycle()
+        .take(chunks.len() * REPLAY_ROUNDS)
+        .cloned()
+        .collect()
+}
+
+fn bench_frame_parsers(c: &mut Criterion) {
+    let chunks = load_chunks();
+    let total_bytes: usize = chunks.iter().map(Vec::len).sum();
+
+    let mut group = c.benchmark_group("dumped_btatt_values");
+    group.throughput(Throughput::Bytes(total_bytes as u64));
+    group.bench_function("state_machine", |b| {
+        b.iter_batched_ref(
+            FrameParser::new,
+            |parser| {
+                let mut frames = Vec::new();
+                for chunk in &chunks {
+                    frames.extend(parser.feed(chunk));
+                }
+                frames
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("regex", |b| {
+        b.iter_batched_ref(
+            || RegexFrameParser {
+                buffered_bytes: Vec::new(),
+            },
+            |parser| {
+                let mut frames = Vec::new();
+                for chunk in &chunks {
+                    parser.parse_chunk(chunk, &mut frames);
+                }
+                frames
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn bench_decoding(c: &mut Criterion) {
+    let chunks = load_chunks();
+    let total_bytes: usize = chunks.iter().map(Vec::len).sum();
+    let decoder = Decoder::new();
+
+    let mut group = c.benchmark_group("decode_dumped_btatt_values");
+    group.throughput(Throughput::Bytes(total_bytes as u64));
+    group.bench_function("owned_frames", |b| {
+        b.iter_batched_ref(
+            FrameParser::new,
+            |parser| {
+                let mut decoded = 0;
+                for chunk in &chunks {
+                    parser.push(chunk);
+                    while let Some(frame) = parser.next_frame() {
+                        decoded += decoder.try_decode(&frame).is_ok() as usize;
+                    }
+                }
+                decoded
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("borrowed_frames", |b| {
+        b.iter_batched_ref(
+            FrameParser::new,
+            |parser| {
+                let mut decoded = 0;
+                for chunk in &chunks {
+                    parser.push(chunk);
+                    while let Some(frame) = parser.next_frame_ref() {
+                        decoded += decoder.decode(frame.0).is_ok() as usize;
+                    }
+                }
+                decoded
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_frame_parsers, bench_decoding);
+criterion_main!(benches);
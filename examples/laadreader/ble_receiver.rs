@@ -19,11 +19,20 @@
  * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
  * SOFTWARE.
  */
-use btleplug::api::{Central, CharPropFlags, Manager as _, Peripheral, ScanFilter, WriteType};
+use btleplug::api::{
+    Central, CharPropFlags, Characteristic, Manager as _, Peripheral, ScanFilter,
+    ValueNotification, WriteType,
+};
 use btleplug::platform::Manager;
+use futures::stream::BoxStream;
 use futures::StreamExt;
+use laad::command::Command;
+use laad::encoder::FrameEncoder;
+use laad::protocol::pgn;
+use laad::transport::{Transport, TransportEvent};
 use laad::types::Bytes;
 use std::error::Error;
+use std::io;
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::time;
@@ -35,56 +44,59 @@ use uuid::Uuid;
 // tshark -r btlog_pre_filter.log -Y "(bthci_acl.src.bd_addr[4:2] == 31:d8) && (btatt.opcode == 0x1d)" -T fields -e btatt.value
 
 pub struct BleReceiver {
-    tx: mpsc::Sender<Bytes>,
+    peripheral: btleplug::platform::Peripheral,
+    characteristic: Characteristic,
+    notifications: BoxStream<'static, ValueNotification>,
+    connected: Option<bool>,
 }
 
 const PERIPHERAL_NAME_MATCH_FILTER: &str = "TBS";
 const TX_RX_CHARACTERISTIC_UUID: Uuid = Uuid::from_u128(0x65333333_A115_11E2_9E9A_0800200CA102);
 const TIMEOUT_SECS: u64 = 20;
-const REQUEST_FOR_ADDRESS_CLAIMED: [u8; 11] = [
-    0xAA, 0xFD, 0x00, 0x00, 0xEA, 0x03, 0x00, 0xEE, 0x00, 0x28, 0x99,
-];
-const SEND_ALL_COMMAND: [u8; 16] = [
-    0xAA, 0xFD, 0x00, 0x03, 0xF0, 0x08, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x10, 0x99,
-];
+const SOURCE_ADDRESS: u8 = 0xFD;
+const DEVICE_ADDRESS: u8 = 0x00;
+const PGN_SEND_ALL: u16 = 0xF003;
 
 impl BleReceiver {
-    pub fn new(tx: mpsc::Sender<Bytes>) -> Self {
-        Self { tx }
-    }
-
-    async fn send_requests(peripheral: &impl Peripheral) {
+    /// Connects to the first matching peripheral and subscribes to its notifications.
+    pub async fn connect() -> Result<Self, Box<dyn Error>> {
+        let peripheral = Self::connect_notify().await?;
         let characteristic = peripheral
             .characteristics()
             .into_iter()
             .find(|c| c.uuid == TX_RX_CHARACTERISTIC_UUID)
             .unwrap();
-        if let Err(err) = peripheral
-            .write(
-                &characteristic,
-                &REQUEST_FOR_ADDRESS_CLAIMED,
-                WriteType::WithoutResponse,
-            )
-            .await
-        {
-            error!("Error sending REQUEST_FOR_ADDRESS_CLAIMED: {:?}", err);
-        } else {
-            debug!("Sent REQUEST_FOR_ADDRESS_CLAIMED");
+        let notifications = peripheral.notifications().await?;
+        Ok(Self {
+            peripheral,
+            characteristic,
+            notifications,
+            connected: None,
+        })
+    }
+
+    /// Requests the address claim once, then all data periodically.
+    pub async fn send_requests(tx: mpsc::Sender<Bytes>) {
+        let encoder = FrameEncoder::new();
+        let request_for_address_claimed = encoder.encode_frame(
+            &Command::Request {
+                pgn: pgn::ADDRESS_CLAIMED,
+                data_page: 0,
+            }
+            .to_frame(SOURCE_ADDRESS, DEVICE_ADDRESS),
+        );
+        let send_all_command =
+            encoder.encode(SOURCE_ADDRESS, DEVICE_ADDRESS, PGN_SEND_ALL, &[0xFF; 8]);
+        if tx.send(Bytes(request_for_address_claimed)).await.is_err() {
+            return;
         }
+        debug!("Sent REQUEST_FOR_ADDRESS_CLAIMED");
         time::sleep(Duration::from_secs(3)).await;
         loop {
-            if let Err(err) = peripheral
-                .write(
-                    &characteristic,
-                    &SEND_ALL_COMMAND,
-                    WriteType::WithoutResponse,
-                )
-                .await
-            {
-                error!("Error sending SEND_ALL_COMMAND: {:?}", err);
-            } else {
-                debug!("Sent SEND_ALL_COMMAND");
+            if tx.send(Bytes(send_all_command.clone())).await.is_err() {
+                return;
             }
+            debug!("Sent SEND_ALL_COMMAND");
             time::sleep(Duration::from_secs(10)).await;
         }
     }
@@ -162,44 +174,45 @@ impl BleReceiver {
             "No matching peripheral found",
         )))
     }
+}
 
-    pub async fn start_receiving(&mut self) {
-        let peripheral = match Self::connect_notify().await {
-            Ok(peripheral) => peripheral,
-            Err(err) => {
-                error!("Error connecting to peripheral: {:?}", err);
-                return;
+impl Transport for BleReceiver {
+    async fn read(&mut self) -> io::Result<TransportEvent> {
+        match self.connected {
+            None => {
+                self.connected = Some(true);
+                return Ok(TransportEvent::Connected);
             }
-        };
-
-        let mut notification_stream = match peripheral.notifications().await {
-            Ok(stream) => stream,
-            Err(err) => {
-                error!("Error getting notifications stream: {:?}", err);
-                return;
+            Some(false) => return Ok(TransportEvent::Closed),
+            Some(true) => {}
+        }
+        match self.notifications.next().await {
+            Some(notification) => {
+                let hex_string: String =
+                    notification
+                        .value
+                        .iter()
+                        .fold(String::new(), |mut acc, byte| {
+                            acc.push_str(&format!("{:02X}", byte));
+                            acc
+                        });
+                debug!(
+                    "Received notification: 0x{}, sending to parser.",
+                    hex_string
+                );
+                Ok(TransportEvent::Received(Bytes(notification.value)))
+            }
+            None => {
+                self.connected = Some(false);
+                Ok(TransportEvent::Disconnected)
             }
-        };
-
-        let peripheral_clone = peripheral.clone();
-        tokio::spawn(async move {
-            Self::send_requests(&peripheral_clone).await;
-        });
-
-        while let Some(notification) = notification_stream.next().await {
-            let hex_string: String =
-                notification
-                    .value
-                    .iter()
-                    .fold(String::new(), |mut acc, byte| {
-                        acc.push_str(&format!("{:02X}", byte));
-                        acc
-                    });
-            debug!(
-                "Received notification: 0x{}, sending to parser.",
-                hex_string
-            );
-            let bytes = Bytes(notification.value);
-            self.tx.send(bytes).await.unwrap();
         }
     }
+
+    async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.peripheral
+            .write(&self.characteristic, bytes, WriteType::WithoutResponse)
+            .await
+            .map_err(io::Error::other)
+    }
 }
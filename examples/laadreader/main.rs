@@ -20,7 +20,7 @@
  * SOFTWARE.
  */
 use replay_sender::ReplaySender;
-use tokio::sync::mpsc::Sender;
+use tokio::sync::mpsc::{Receiver, Sender};
 
 use ble_receiver::BleReceiver;
 use tokio::sync::mpsc;
@@ -29,12 +29,16 @@ mod ble_receiver;
 mod random_sender;
 mod replay_sender;
 
-use laad::{decoder, frameparser::FrameParser, protocol::TbsPg, types::Bytes};
+use laad::{decoder, frameparser::FrameParser, transport::run_transport, types::Bytes};
 use random_sender::RandomSender;
 use tracing::{error, info, Level};
 use tracing_subscriber::FmtSubscriber;
 
-fn configure_and_run_source(bytes_tx: Sender<Bytes>) {
+fn configure_and_run_source(
+    bytes_tx: Sender<Bytes>,
+    outgoing_tx: Sender<Bytes>,
+    outgoing_rx: Receiver<Bytes>,
+) {
     let matches = clap::Command::new("laadreader")
         .arg(
             clap::Arg::new("ble")
@@ -53,20 +57,23 @@ fn configure_and_run_source(bytes_tx: Sender<Bytes>) {
         .get_matches_from(std::env::args());
 
     if matches.get_flag("ble") {
-        let mut sender = BleReceiver::new(bytes_tx);
         tokio::spawn(async move {
-            sender.start_receiving().await;
+            let receiver = match BleReceiver::connect().await {
+                Ok(receiver) => receiver,
+                Err(err) => {
+                    error!("Error connecting to peripheral: {:?}", err);
+                    return;
+                }
+            };
+            tokio::spawn(BleReceiver::send_requests(outgoing_tx));
+            if let Err(err) = run_transport(receiver, bytes_tx, outgoing_rx).await {
+                error!("BLE transport failed: {}", err);
+            }
         });
     } else if matches.get_flag("replay") {
-        let mut sender = ReplaySender::new(bytes_tx);
-        tokio::spawn(async move {
-            sender.send_bytes().await;
-        });
+        tokio::spawn(run_transport(ReplaySender::new(), bytes_tx, outgoing_rx));
     } else {
-        let mut sender = RandomSender::new(bytes_tx);
-        tokio::spawn(async move {
-            sender.send_bytes().await;
-        });
+        tokio::spawn(run_transport(RandomSender::new(), bytes_tx, outgoing_rx));
     }
 }
 
@@ -82,9 +89,11 @@ async fn main() {
     let (bytes_tx, bytes_rx) = mpsc::channel(5);
     let (frames_tx, mut frames_rx) = mpsc::channel(5);
 
-    configure_and_run_source(bytes_tx);
+    let (outgoing_tx, outgoing_rx) = mpsc::channel(5);
+
+    configure_and_run_source(bytes_tx, outgoing_tx, outgoing_rx);
 
-    // Source sends bytes to bytes_tx using bytes_tx.send(Bytes(bytes)).await.
+    // The transport sends received bytes to bytes_tx and writes bytes sent to outgoing_tx.
 
     let mut frame_parser = FrameParser::new();
 
@@ -93,14 +102,16 @@ async fn main() {
     });
 
     while let Some(frame) = frames_rx.recv().await {
-        let decoder = decoder::Decoder {};
-        let decoded = decoder.decode_frame(frame);
-        match decoded {
-            TbsPg::Unknown => {
-                error!("Received unknown frame");
+        let decoder = decoder::Decoder::new();
+        match decoder.try_decode(&frame) {
+            Ok(message) => {
+                info!(
+                    "Decoded frame from 0x{:02X}: {:?}",
+                    message.header.source, message.body
+                );
             }
-            _ => {
-                info!("Decoded frame: {:?}", decoded);
+            Err(err) => {
+                error!("Failed to decode frame: {}", err);
             }
         }
     }
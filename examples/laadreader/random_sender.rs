@@ -21,9 +21,11 @@
  */
 use rand::prelude::SliceRandom;
 use rand::Rng;
-use tokio::sync::mpsc;
+use std::io;
 
+use laad::transport::{Transport, TransportEvent};
 use laad::types::Bytes;
+use tracing::debug;
 
 const EXAMPLE_PACKETS: &[&[u8]] = &[
     &[0xAA, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x03, 0x99], // [HRTBT]
@@ -63,14 +65,12 @@ const BROKEN_PACKETS: &[&[u8]] = &[
 const WORKING_BUFFER_SIZE: usize = 20;
 
 pub struct RandomSender {
-    tx: mpsc::Sender<Bytes>,
     buffer: std::collections::VecDeque<u8>,
 }
 
 impl RandomSender {
-    pub fn new(tx: mpsc::Sender<Bytes>) -> Self {
+    pub fn new() -> Self {
         Self {
-            tx,
             buffer: std::collections::VecDeque::new(),
         }
     }
@@ -88,18 +88,20 @@ impl RandomSender {
             self.buffer.extend(packet.iter().copied());
         }
     }
+}
 
-    pub async fn send_bytes(&mut self) {
-        loop {
-            self.fill_buffer_if_nedeed();
-            let chunk_size =
-                rand::thread_rng().gen_range(WORKING_BUFFER_SIZE * 40 / 100..=WORKING_BUFFER_SIZE);
-            let bytes: Vec<u8> = self.buffer.drain(..chunk_size).collect();
-            self.tx
-                .send(Bytes(bytes))
-                .await
-                .expect("Failed to send bytes");
-            // tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-        }
+impl Transport for RandomSender {
+    async fn read(&mut self) -> io::Result<TransportEvent> {
+        self.fill_buffer_if_nedeed();
+        let chunk_size =
+            rand::thread_rng().gen_range(WORKING_BUFFER_SIZE * 40 / 100..=WORKING_BUFFER_SIZE);
+        let bytes: Vec<u8> = self.buffer.drain(..chunk_size).collect();
+        // tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        Ok(TransportEvent::Received(Bytes(bytes)))
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        debug!("Random sender discards {} written bytes", bytes.len());
+        Ok(())
     }
 }
@@ -20,23 +20,19 @@
  * SOFTWARE.
  */
 
-use std::io::BufRead;
-
-use tokio::sync::mpsc;
+use std::io::{self, BufRead};
 
+use laad::transport::{Transport, TransportEvent};
 use laad::types::Bytes;
+use tracing::debug;
 
 pub struct ReplaySender {
-    tx: mpsc::Sender<Bytes>,
     replay_file: Option<std::iter::Cycle<std::vec::IntoIter<std::string::String>>>,
 }
 
 impl ReplaySender {
-    pub fn new(tx: mpsc::Sender<Bytes>) -> Self {
-        Self {
-            tx,
-            replay_file: None,
-        }
+    pub fn new() -> Self {
+        Self { replay_file: None }
     }
 
     pub fn open_file_if_needed(&mut self) {
@@ -50,20 +46,22 @@ impl ReplaySender {
             self.replay_file = Some(lines.into_iter().cycle());
         }
     }
+}
 
-    pub async fn send_bytes(&mut self) {
+impl Transport for ReplaySender {
+    async fn read(&mut self) -> io::Result<TransportEvent> {
         self.open_file_if_needed();
-        loop {
-            let line = self.replay_file.as_mut().unwrap().next().unwrap();
-            let bytes = (0..line.len())
-                .step_by(2)
-                .map(|i| u8::from_str_radix(&line[i..i + 2], 16).expect("Failed to decode hex"))
-                .collect::<Vec<u8>>();
-            self.tx
-                .send(Bytes(bytes))
-                .await
-                .expect("Failed to send bytes");
-            // tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-        }
+        let line = self.replay_file.as_mut().unwrap().next().unwrap();
+        let bytes = (0..line.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&line[i..i + 2], 16).expect("Failed to decode hex"))
+            .collect::<Vec<u8>>();
+        // tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        Ok(TransportEvent::Received(Bytes(bytes)))
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        debug!("Replay discards {} written bytes", bytes.len());
+        Ok(())
     }
 }
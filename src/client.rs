@@ -0,0 +1,416 @@
+/*
+ * Copyright (c) 2024 Dominik Röttsches
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use crate::{
+    command::{Command, CommandError},
+    decoder::Decoder,
+    encoder::FrameEncoder,
+    protocol::Message,
+    types::{Bytes, Frame, FrameHeader},
+};
+use std::{collections::VecDeque, fmt, time::Duration};
+use tokio::{sync::mpsc, time::Instant};
+use tracing::{debug, warn};
+
+/// Default time to wait for the response to a command, per attempt.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Default number of times a command is sent again if there is no response.
+const DEFAULT_RETRIES: u32 = 2;
+/// Default number of messages kept while waiting for responses.
+const DEFAULT_RECEIVED_CAPACITY: usize = 64;
+/// Address used by the laadreader example, not claimed by TBS devices.
+const DEFAULT_SOURCE_ADDRESS: u8 = 0xFD;
+
+/// Errors of [`Client::send`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientError {
+    /// The device rejected the command.
+    Rejected(CommandError),
+    /// The device did not respond to any of the attempts.
+    Timeout { attempts: u32 },
+    /// The channel of incoming frames or outgoing bytes was closed.
+    Disconnected,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Rejected(err) => write!(f, "Command rejected: {}", err),
+            ClientError::Timeout { attempts } => {
+                write!(f, "No response after {} attempts", attempts)
+            }
+            ClientError::Disconnected => write!(f, "Disconnected"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<CommandError> for ClientError {
+    fn from(err: CommandError) -> Self {
+        ClientError::Rejected(err)
+    }
+}
+
+/// Client sends [commands](Command) to devices and waits for their responses.
+///
+/// The client receives frames, for example from
+/// [`FrameParser::parse_frames`](crate::frameparser::FrameParser::parse_frames), and sends the
+/// encoded commands as [`Bytes`] to the transport. Messages received while waiting for a
+/// response that do not answer the command are kept and returned by [`Client::recv`]. At most
+/// [`with_received_capacity`](Client::with_received_capacity) messages are kept, the oldest
+/// messages are dropped first.
+///
+/// # Example
+///
+/// ```rust
+/// use laad::{
+///     client::{Client, ClientError},
+///     command::{Command, CommandError},
+///     protocol::pgn,
+///     types::Frame,
+/// };
+/// use std::time::Duration;
+/// use tokio::sync::mpsc;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let (frames_tx, frames_rx) = mpsc::channel(5);
+///     let (bytes_tx, _bytes_rx) = mpsc::channel(5);
+///     let mut client = Client::new(frames_rx, bytes_tx).with_timeout(Duration::from_millis(100));
+///
+///     // The device at address 0x00 cannot respond to requests for its register dump.
+///     let cannot_respond = [0x03, 0xFF, 0xFF, 0xFF, 0xFF, 0xEA, 0xFF, 0x01];
+///     let ack = Frame::build(0x00, 0xFD, pgn::ACKNOWLEDGEMENT, &cannot_respond);
+///     frames_tx.send(ack).await.unwrap();
+///
+///     let request = Command::Request {
+///         pgn: pgn::REGISTER_DUMP,
+///         data_page: 1,
+///     };
+///     let result = client.send(0x00, &request).await;
+///     assert_eq!(
+///         result.unwrap_err(),
+///         ClientError::Rejected(CommandError::CannotRespond)
+///     );
+/// }
+/// ```
+pub struct Client {
+    frames: mpsc::Receiver<Frame>,
+    bytes: mpsc::Sender<Bytes>,
+    decoder: Decoder,
+    encoder: FrameEncoder,
+    source: u8,
+    timeout: Duration,
+    retries: u32,
+    received: VecDeque<Message>,
+    received_capacity: usize,
+}
+
+impl Client {
+    /// Creates a client receiving frames from `frames` and sending bytes to `bytes`.
+    pub fn new(frames: mpsc::Receiver<Frame>, bytes: mpsc::Sender<Bytes>) -> Self {
+        Self {
+            frames,
+            bytes,
+            decoder: Decoder::new(),
+            encoder: FrameEncoder::new(),
+            source: DEFAULT_SOURCE_ADDRESS,
+            timeout: DEFAULT_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+            received: VecDeque::new(),
+            received_capacity: DEFAULT_RECEIVED_CAPACITY,
+        }
+    }
+
+    /// Sets the decoder for received frames, for example with [registered](Decoder::register)
+    /// decoders.
+    pub fn with_decoder(mut self, decoder: Decoder) -> Self {
+        self.decoder = decoder;
+        self
+    }
+
+    /// Sets the address commands are sent from, 0xFD by default.
+    pub fn with_source_address(mut self, source: u8) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Sets the time to wait for a response to each attempt, 2 seconds by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how often a command is sent again if there is no response, 2 times by default.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets how many messages received while waiting for responses are kept for
+    /// [`recv`](Client::recv), 64 by default.
+    pub fn with_received_capacity(mut self, received_capacity: usize) -> Self {
+        self.received_capacity = received_capacity;
+        self
+    }
+
+    /// Sends the command to the device at `destination` and waits for the response, see
+    /// [`Command::check_response`]. Returns the message confirming the command, for example
+    /// the requested message for [`Command::Request`].
+    ///
+    /// For commands sent to [`FrameHeader::BROADCAST_ADDRESS`], the first response of any
+    /// device is returned.
+    pub async fn send(
+        &mut self,
+        destination: u8,
+        command: &Command,
+    ) -> Result<Message, ClientError> {
+        let bytes = self
+            .encoder
+            .encode_frame(&command.to_frame(self.source, destination));
+        let attempts = self.retries + 1;
+        for attempt in 1..=attempts {
+            self.bytes
+                .send(Bytes(bytes.clone()))
+                .await
+                .map_err(|_| ClientError::Disconnected)?;
+            debug!(
+                "Sent {:?} to {:02X}, attempt {}",
+                command, destination, attempt
+            );
+
+            let deadline = Instant::now() + self.timeout;
+            loop {
+                let frame = match tokio::time::timeout_at(deadline, self.frames.recv()).await {
+                    Ok(Some(frame)) => frame,
+                    Ok(None) => return Err(ClientError::Disconnected),
+                    Err(_) => break,
+                };
+                let message = match self.decoder.try_decode(&frame) {
+                    Ok(message) => message,
+                    Err(err) => {
+                        warn!("Dropping frame while waiting for a response: {}", err);
+                        continue;
+                    }
+                };
+                let from_destination = destination == FrameHeader::BROADCAST_ADDRESS
+                    || message.header.source == destination;
+                match command.check_response(&message) {
+                    Some(result) if from_destination => {
+                        return result.map(|()| message).map_err(ClientError::from)
+                    }
+                    _ => self.keep_received(message),
+                }
+            }
+        }
+        Err(ClientError::Timeout { attempts })
+    }
+
+    /// Keeps a message for [`recv`](Client::recv), dropping the oldest kept message when full.
+    fn keep_received(&mut self, message: Message) {
+        if self.received.len() >= self.received_capacity {
+            match self.received.pop_front() {
+                Some(dropped) => warn!(
+                    "Dropping message from {:02X} received while waiting for a response",
+                    dropped.header.source
+                ),
+                None => return,
+            }
+        }
+        self.received.push_back(message);
+    }
+
+    /// Receives the next message, returning messages kept while waiting for responses first.
+    /// Frames that fail to decode are skipped. Returns `None` when the channel of incoming frames
+    /// is closed.
+    pub async fn recv(&mut self) -> Option<Message> {
+        if let Some(message) = self.received.pop_front() {
+            return Some(message);
+        }
+        while let Some(frame) = self.frames.recv().await {
+            match self.decoder.try_decode(&frame) {
+                Ok(message) => return Some(message),
+                Err(err) => warn!("Dropping frame: {}", err),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        command::tests::acknowledgement,
+        frameparser::FrameParser,
+        protocol::{
+            pgn, AcknowledgementType, BankId, BankMessageKind, InstallerLock, OperatingMode, TbsPg,
+        },
+    };
+
+    const DEVICE_ADDRESS: u8 = 0x00;
+
+    /// Simulates a device answering each received frame with the frames returned by `respond`.
+    fn device(
+        respond: impl Fn(Frame) -> Vec<Frame> + Send + 'static,
+    ) -> (Client, mpsc::Sender<Frame>, tokio::task::JoinHandle<usize>) {
+        let (frames_tx, frames_rx) = mpsc::channel(16);
+        let (bytes_tx, mut bytes_rx) = mpsc::channel::<Bytes>(16);
+        let device_tx = frames_tx.clone();
+        let device = tokio::spawn(async move {
+            let mut parser = FrameParser::new();
+            let mut received = 0;
+            while let Some(Bytes(bytes)) = bytes_rx.recv().await {
+                for frame in parser.feed(&bytes) {
+                    received += 1;
+                    for response in respond(frame) {
+                        device_tx.send(response).await.unwrap();
+                    }
+                }
+            }
+            received
+        });
+        let client = Client::new(frames_rx, bytes_tx).with_timeout(Duration::from_millis(50));
+        (client, frames_tx, device)
+    }
+
+    #[tokio::test]
+    async fn test_send_acknowledged() {
+        let (mut client, _frames_tx, _device) = device(|frame| {
+            vec![acknowledgement(
+                AcknowledgementType::PositiveAcknowledgement,
+                u16::from_le_bytes([frame.0[6], frame.0[7]]),
+                frame.0[8],
+            )]
+        });
+        let command = Command::Request {
+            pgn: BankId::BANK_1.pgn(BankMessageKind::BasicSetup),
+            data_page: 1,
+        };
+        let message = client.send(DEVICE_ADDRESS, &command).await.unwrap();
+        assert!(matches!(message.body, TbsPg::Acknowledgement(_)));
+    }
+
+    #[tokio::test]
+    async fn test_send_rejected() {
+        let (mut client, _frames_tx, _device) = device(|frame| {
+            vec![acknowledgement(
+                AcknowledgementType::CannotRespond,
+                u16::from_le_bytes([frame.0[6], frame.0[7]]),
+                frame.0[8],
+            )]
+        });
+        assert_eq!(
+            client
+                .send(
+                    DEVICE_ADDRESS,
+                    &Command::Request {
+                        pgn: 0xF020,
+                        data_page: 1,
+                    }
+                )
+                .await
+                .unwrap_err(),
+            ClientError::Rejected(CommandError::CannotRespond)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_keeps_unrelated_messages() {
+        let status = [0x0A, 0xFF, 0xFF, 0x3F, 0xFF, 0xFF, 0xFF, 0xFF];
+        let (mut client, _frames_tx, _device) = device(move |_| {
+            vec![
+                Frame::build(DEVICE_ADDRESS, 0xFF, pgn::HEARTBEAT, &[]),
+                // A status from another device does not answer the request.
+                Frame::build(0x01, 0xFF, pgn::OPERATING_MODE, &status),
+                Frame::build(DEVICE_ADDRESS, 0xFF, pgn::OPERATING_MODE, &status),
+            ]
+        });
+        let request = Command::Request {
+            pgn: pgn::OPERATING_MODE,
+            data_page: 1,
+        };
+        let message = client.send(DEVICE_ADDRESS, &request).await.unwrap();
+        match message.body {
+            TbsPg::OperatingModeStatus(status) => {
+                assert_eq!(message.header.source, DEVICE_ADDRESS);
+                assert_eq!(status.mode, OperatingMode::DeviceOn);
+                assert_eq!(status.installer_lock, InstallerLock::InstallerLockOff);
+            }
+            other => panic!("Unexpected response {:?}", other),
+        }
+        assert!(matches!(
+            client.recv().await.unwrap().body,
+            TbsPg::Heartbeat
+        ));
+        assert_eq!(client.recv().await.unwrap().header.source, 0x01);
+    }
+
+    #[tokio::test]
+    async fn test_send_retries_until_timeout() {
+        let (client, frames_tx, device) = device(|_| Vec::new());
+        let mut client = client.with_retries(3);
+        assert_eq!(
+            client
+                .send(
+                    DEVICE_ADDRESS,
+                    &Command::Request {
+                        pgn: 0xF020,
+                        data_page: 1,
+                    }
+                )
+                .await
+                .unwrap_err(),
+            ClientError::Timeout { attempts: 4 }
+        );
+        drop(client);
+        drop(frames_tx);
+        assert_eq!(device.await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_send_drops_oldest_kept_messages() {
+        let (client, frames_tx, _device) = device(|_| {
+            (1..=3)
+                .map(|source| Frame::build(source, 0xFF, pgn::HEARTBEAT, &[]))
+                .collect()
+        });
+        let mut client = client.with_retries(0).with_received_capacity(2);
+        assert_eq!(
+            client
+                .send(
+                    DEVICE_ADDRESS,
+                    &Command::Request {
+                        pgn: 0xF020,
+                        data_page: 1,
+                    }
+                )
+                .await
+                .unwrap_err(),
+            ClientError::Timeout { attempts: 1 }
+        );
+        drop(frames_tx);
+        assert_eq!(client.recv().await.unwrap().header.source, 0x02);
+        assert_eq!(client.recv().await.unwrap().header.source, 0x03);
+    }
+}
@@ -0,0 +1,116 @@
+/*
+ * Copyright (c) 2024 Dominik Röttsches
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use crate::{encoder::FrameEncoder, frameparser::FrameParser, types::Frame};
+use bytes::{Buf, BytesMut};
+use tokio_util::codec;
+
+/// TbsCodec implements the `tokio_util` codec traits for the TBS wire format, so that any
+/// `AsyncRead + AsyncWrite` wrapped in `tokio_util::codec::Framed` becomes a `Stream` of received
+/// [`Frame`]s and a `Sink` for frames to send.
+///
+/// Decoding uses a [`FrameParser`], with the same resynchronization and limits on buffered bytes.
+/// Encoding bytestuffs frames using a [`FrameEncoder`].
+///
+/// Only available with the `codec` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use futures::{SinkExt, StreamExt};
+/// use laad::{codec::TbsCodec, types::Frame};
+/// use tokio_util::codec::Framed;
+/// #[tokio::main]
+/// async fn main() {
+///   let (client, server) = tokio::io::duplex(64);
+///   let mut client = Framed::new(client, TbsCodec::new());
+///   let mut server = Framed::new(server, TbsCodec::new());
+///   client.send(Frame::build(0x00, 0xFF, 0xFFFF, &[])).await.unwrap();
+///   let frame = server.next().await.unwrap().unwrap();
+///   assert_eq!(frame.0.len(), 8);
+/// }
+/// ```
+#[derive(Default)]
+pub struct TbsCodec {
+    parser: FrameParser,
+    encoder: FrameEncoder,
+}
+
+impl TbsCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl codec::Decoder for TbsCodec {
+    type Item = Frame;
+    type Error = std::io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Frame>, Self::Error> {
+        if !src.is_empty() {
+            self.parser.push(src);
+            src.advance(src.len());
+        }
+        Ok(self.parser.next_frame())
+    }
+}
+
+impl codec::Encoder<Frame> for TbsCodec {
+    type Error = std::io::Error;
+
+    fn encode(&mut self, frame: Frame, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.extend_from_slice(&self.encoder.encode_frame(&frame));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    #[test]
+    fn test_decode_split_and_garbage() {
+        let mut codec = TbsCodec::new();
+        let mut src = BytesMut::from(&[0x01, 0x99, 0xAA, 0x00, 0xFF, 0xFF][..]);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        assert!(src.is_empty());
+        src.extend_from_slice(&[0xFF, 0x00, 0x03, 0x99, 0xAA, 0x00, 0xFF, 0xFF, 0xFF, 0x00]);
+        let frame = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(&*frame.0, &[0xAA, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x03, 0x99]);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        src.extend_from_slice(&[0x03, 0x99]);
+        assert!(codec.decode(&mut src).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let mut codec = TbsCodec::new();
+        let mut buffer = BytesMut::new();
+        let frame = Frame::build(0xFD, 0x00, 0xF003, &[0xAA, 0x99, 0xA9]);
+        let expected = frame.0.clone();
+        codec.encode(frame, &mut buffer).unwrap();
+        assert_eq!(buffer.iter().filter(|&&b| b == 0xAA).count(), 1);
+        let decoded = codec.decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(decoded.0, expected);
+    }
+}
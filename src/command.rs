@@ -0,0 +1,693 @@
+/*
+ * Copyright (c) 2024 Dominik Röttsches
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+#[cfg(feature = "experimental")]
+use crate::protocol::{
+    BankCapacity, BankEnable, BankId, BankMessageKind, BasicSetup, InstallerLock, OperatingMode,
+    OperatingModeStatus,
+};
+use crate::{
+    protocol::{pgn, AcknowledgementType, Message, TbsPg},
+    types::Frame,
+};
+use std::fmt;
+
+/// A command sent to a device, built into a [`Frame`] with [`Command::to_frame`] and encoded
+/// for sending with a [`FrameEncoder`](crate::encoder::FrameEncoder).
+///
+/// # Example
+///
+/// ```rust
+/// use laad::{
+///     command::Command,
+///     encoder::FrameEncoder,
+///     protocol::{BankId, BankMessageKind},
+/// };
+///
+/// // Request the bank status of bank 1 from the device at address 0x00.
+/// let command = Command::Request {
+///     pgn: BankId::BANK_1.pgn(BankMessageKind::BankStatus),
+///     data_page: 1,
+/// };
+/// let frame = command.to_frame(0xFD, 0x00);
+/// let bytes = FrameEncoder::new().encode_frame(&frame);
+/// assert_eq!(
+///     bytes,
+///     [0xAA, 0xFD, 0x00, 0x00, 0xEA, 0x03, 0x1A, 0xF0, 0x01, 0x0B, 0x99]
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Requests the message with the given PGN. The device answers with the message, or with an
+    /// [acknowledgement](crate::protocol::Acknowledgement) if it cannot send it.
+    ///
+    /// The request carries the data page of the PGN, which frames do not. The device
+    /// acknowledged PGN 0xF003 on data page 1 in `dump/dumped_btatt_values.log`, and the address
+    /// claim (PGN 0xEE00) is requested on data page 0. The data page of other PGNs has not been
+    /// checked against a device.
+    Request { pgn: u16, data_page: u8 },
+    /// Writes the basic setup of a bank. Fields set to `ParameterNotAvailable` or
+    /// `ParameterUnavailable` are left unchanged by the device.
+    ///
+    /// The device confirms the setup with an [acknowledgement](crate::protocol::Acknowledgement)
+    /// for the basic setup PGN of the bank, [`AcknowledgementType::AccessDenied`] if the
+    /// [installer lock](crate::protocol::InstallerLock) is on.
+    ///
+    /// Only available with the `experimental` feature: the layout of the frame is the layout of
+    /// the basic setup the device sends, with the remaining bits of the bank enable word set. It
+    /// is not documented and has not been verified against a device. The device sends 0xBF
+    /// rather than 0xFF in the second byte, for reasons not known.
+    #[cfg(feature = "experimental")]
+    WriteBasicSetup { bank: BankId, setup: BasicSetup },
+    /// Sets the operating mode and the installer lock of the device. A mode of `None` and an
+    /// installer lock of [`InstallerLock::ParameterNotAvailable`] are left unchanged.
+    ///
+    /// The device confirms the change by sending its [operating mode status](OperatingModeStatus).
+    ///
+    /// Only available with the `experimental` feature: the layout of the frame is the layout of
+    /// the operating mode status the device sends, with the remaining bits set. It is not
+    /// documented and has not been verified against a device.
+    #[cfg(feature = "experimental")]
+    SetOperatingMode {
+        mode: Option<OperatingModeSetting>,
+        installer_lock: InstallerLock,
+    },
+    /// Resets the consumed charge of a bank to 0 Ah, by writing it to the power and charge PGN of
+    /// the bank.
+    ///
+    /// Only available with the `experimental` feature: writing to the power and charge PGN, and
+    /// the layout of the frame, are inferred from the message the device sends. Neither is
+    /// documented nor verified against a device.
+    #[cfg(feature = "experimental")]
+    ResetConsumedAmpHours { bank: BankId },
+    /// Synchronizes the state of charge of a bank to 100 %, as after a full charge, by writing
+    /// it to the bank status PGN of the bank.
+    ///
+    /// Only available with the `experimental` feature, like
+    /// [`ResetConsumedAmpHours`](Command::ResetConsumedAmpHours).
+    #[cfg(feature = "experimental")]
+    SynchronizeStateOfCharge { bank: BankId },
+    /// Resets the state of health of a bank to 100 %, restarting its learning, by writing it to
+    /// the bank status PGN of the bank.
+    ///
+    /// Only available with the `experimental` feature, like
+    /// [`ResetConsumedAmpHours`](Command::ResetConsumedAmpHours).
+    #[cfg(feature = "experimental")]
+    ResetStateOfHealth { bank: BankId },
+}
+
+/// The operating modes that can be set with [`Command::SetOperatingMode`]. The other
+/// [operating modes](OperatingMode) are only reported by the device.
+///
+/// Only available with the `experimental` feature.
+#[cfg(feature = "experimental")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatingModeSetting {
+    DeviceOff,
+    DeviceOn,
+    DeviceOnNightMode,
+}
+
+#[cfg(feature = "experimental")]
+impl From<OperatingModeSetting> for OperatingMode {
+    fn from(mode: OperatingModeSetting) -> OperatingMode {
+        match mode {
+            OperatingModeSetting::DeviceOff => OperatingMode::DeviceOff,
+            OperatingModeSetting::DeviceOn => OperatingMode::DeviceOn,
+            OperatingModeSetting::DeviceOnNightMode => OperatingMode::DeviceOnNightMode,
+        }
+    }
+}
+
+/// The reason a device rejected a command, from the
+/// [acknowledgement](crate::protocol::Acknowledgement) it answered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandError {
+    NegativeAcknowledgement,
+    /// The command is not allowed, for example while the installer lock is on.
+    AccessDenied,
+    CannotRespond,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::NegativeAcknowledgement => write!(f, "Command not acknowledged"),
+            CommandError::AccessDenied => write!(f, "Access denied"),
+            CommandError::CannotRespond => write!(f, "Device cannot respond"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl Command {
+    /// Switches the device on.
+    #[cfg(feature = "experimental")]
+    pub fn switch_on() -> Command {
+        Command::set_mode(OperatingModeSetting::DeviceOn)
+    }
+
+    /// Switches the device off.
+    #[cfg(feature = "experimental")]
+    pub fn switch_off() -> Command {
+        Command::set_mode(OperatingModeSetting::DeviceOff)
+    }
+
+    /// Switches the device on in night mode.
+    #[cfg(feature = "experimental")]
+    pub fn night_mode() -> Command {
+        Command::set_mode(OperatingModeSetting::DeviceOnNightMode)
+    }
+
+    /// Turns the installer lock on or off, which protects the setup of the device from changes.
+    #[cfg(feature = "experimental")]
+    pub fn installer_lock(locked: bool) -> Command {
+        Command::SetOperatingMode {
+            mode: None,
+            installer_lock: if locked {
+                InstallerLock::InstallerLockOn
+            } else {
+                InstallerLock::InstallerLockOff
+            },
+        }
+    }
+
+    #[cfg(feature = "experimental")]
+    fn set_mode(mode: OperatingModeSetting) -> Command {
+        Command::SetOperatingMode {
+            mode: Some(mode),
+            installer_lock: InstallerLock::ParameterNotAvailable,
+        }
+    }
+
+    /// Returns the PGN the command is sent with.
+    pub fn pgn(&self) -> u16 {
+        match self {
+            Command::Request { .. } => pgn::REQUEST,
+            #[cfg(feature = "experimental")]
+            Command::WriteBasicSetup { bank, .. } => bank.pgn(BankMessageKind::BasicSetup),
+            #[cfg(feature = "experimental")]
+            Command::SetOperatingMode { .. } => pgn::OPERATING_MODE,
+            #[cfg(feature = "experimental")]
+            Command::ResetConsumedAmpHours { bank } => bank.pgn(BankMessageKind::PowerAndCharge),
+            #[cfg(feature = "experimental")]
+            Command::SynchronizeStateOfCharge { bank } | Command::ResetStateOfHealth { bank } => {
+                bank.pgn(BankMessageKind::BankStatus)
+            }
+        }
+    }
+
+    /// Returns the payload of the command.
+    pub fn payload(&self) -> Vec<u8> {
+        match self {
+            // PGNs are sent as three bytes, the third one being the data page.
+            Command::Request { pgn, data_page } => {
+                let [low, high] = pgn.to_le_bytes();
+                vec![low, high, *data_page]
+            }
+            #[cfg(feature = "experimental")]
+            Command::WriteBasicSetup { setup, .. } => basic_setup_payload(setup),
+            #[cfg(feature = "experimental")]
+            Command::SetOperatingMode {
+                mode,
+                installer_lock,
+            } => operating_mode_payload(*mode, *installer_lock),
+            // Flags and power unchanged, followed by 0 Ah with the offset of 8000000 steps.
+            #[cfg(feature = "experimental")]
+            Command::ResetConsumedAmpHours { .. } => {
+                vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x12, 0x7A]
+            }
+            // Flags unchanged, followed by the state of charge, state of health and remaining
+            // time in 0.01 % and minutes.
+            #[cfg(feature = "experimental")]
+            Command::SynchronizeStateOfCharge { .. } => {
+                vec![0xFF, 0xFF, 0x10, 0x27, 0xFF, 0xFF, 0xFF, 0xFF]
+            }
+            #[cfg(feature = "experimental")]
+            Command::ResetStateOfHealth { .. } => {
+                vec![0xFF, 0xFF, 0xFF, 0xFF, 0x10, 0x27, 0xFF, 0xFF]
+            }
+        }
+    }
+
+    /// Checks whether `message` answers the command. Returns `None` for unrelated messages,
+    /// otherwise whether the device accepted or rejected the command.
+    ///
+    /// Only the message is checked, not whether it was sent by the device the command was sent
+    /// to, compare [`FrameHeader::source`](crate::types::FrameHeader::source) for that.
+    pub fn check_response(&self, message: &Message) -> Option<Result<(), CommandError>> {
+        // The data page of acknowledgements for writes is not known, so only the PGN is matched.
+        let (expected_pgn, expected_data_page) = match self {
+            Command::Request { pgn, data_page } => {
+                if message.header.pgn == *pgn {
+                    return Some(Ok(()));
+                }
+                (*pgn, Some(*data_page))
+            }
+            #[cfg(feature = "experimental")]
+            Command::WriteBasicSetup { .. } => (self.pgn(), None),
+            #[cfg(feature = "experimental")]
+            Command::ResetConsumedAmpHours { .. }
+            | Command::SynchronizeStateOfCharge { .. }
+            | Command::ResetStateOfHealth { .. } => (self.pgn(), None),
+            #[cfg(feature = "experimental")]
+            Command::SetOperatingMode {
+                mode,
+                installer_lock,
+            } => {
+                if let TbsPg::OperatingModeStatus(status) = &message.body {
+                    // Statuses sent before the change was applied are not a confirmation.
+                    return is_operating_mode_set(status, *mode, *installer_lock).then_some(Ok(()));
+                }
+                (self.pgn(), None)
+            }
+        };
+        match &message.body {
+            TbsPg::Acknowledgement(ack)
+                if ack.pgn == expected_pgn
+                    && expected_data_page.is_none_or(|data_page| ack.data_page == data_page) =>
+            {
+                Some(match ack.ack_type {
+                    // Operating mode changes are only confirmed by the status.
+                    #[cfg(feature = "experimental")]
+                    AcknowledgementType::PositiveAcknowledgement
+                        if matches!(self, Command::SetOperatingMode { .. }) =>
+                    {
+                        return None
+                    }
+                    AcknowledgementType::PositiveAcknowledgement => Ok(()),
+                    AcknowledgementType::AccessDenied => Err(CommandError::AccessDenied),
+                    AcknowledgementType::CannotRespond => Err(CommandError::CannotRespond),
+                    AcknowledgementType::NegativeAcknowledgement
+                    | AcknowledgementType::Reserved => Err(CommandError::NegativeAcknowledgement),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Builds the frame to send the command from `source` to `destination`, use
+    /// [`FrameHeader::BROADCAST_ADDRESS`](crate::types::FrameHeader::BROADCAST_ADDRESS) to send
+    /// it to all devices.
+    pub fn to_frame(&self, source: u8, destination: u8) -> Frame {
+        Frame::build(source, destination, self.pgn(), &self.payload())
+    }
+}
+
+/// Encodes the basic setup as decoded by the [decoder](crate::decoder::Decoder), unavailable
+/// parameters as all bits set.
+#[cfg(feature = "experimental")]
+fn basic_setup_payload(setup: &BasicSetup) -> Vec<u8> {
+    let bank_enable: u16 = match setup.bank_enable {
+        BankEnable::Disabled => 0b00,
+        BankEnable::Enabled => 0b01,
+        BankEnable::ParameterUnavailable => 0b11,
+    };
+    let bank_capacity = match setup.bank_capacity {
+        BankCapacity::CapacityAh(capacity) => capacity,
+        BankCapacity::ParameterNotAvailable => 0xFFFF,
+    };
+    let mut payload = Vec::with_capacity(8);
+    payload.extend_from_slice(&(0xFFFC | bank_enable).to_le_bytes());
+    payload.extend_from_slice(&(setup.battery_type as u16).to_le_bytes());
+    payload.extend_from_slice(&bank_capacity.to_le_bytes());
+    payload.push(0xFF);
+    payload.push(setup.bank_name as u8);
+    payload
+}
+
+/// Encodes the operating mode status as decoded by the [decoder](crate::decoder::Decoder),
+/// unavailable parameters as all bits set.
+#[cfg(feature = "experimental")]
+fn operating_mode_payload(
+    mode: Option<OperatingModeSetting>,
+    installer_lock: InstallerLock,
+) -> Vec<u8> {
+    let mode = mode.map_or(OperatingMode::ParameterNotAvailable, OperatingMode::from);
+    let installer_lock: u16 = match installer_lock {
+        InstallerLock::InstallerLockOff => 0b00,
+        InstallerLock::InstallerLockOn => 0b01,
+        InstallerLock::ParameterNotAvailable => 0b11,
+    };
+    let mut payload = Vec::with_capacity(8);
+    payload.push(mode as u8);
+    payload.push(0xFF);
+    payload.extend_from_slice(&(0x3FFF | installer_lock << 14).to_le_bytes());
+    payload.extend_from_slice(&[0xFF; 4]);
+    payload
+}
+
+/// Returns whether the status shows the mode and installer lock set, ignoring parameters that
+/// are not set.
+#[cfg(feature = "experimental")]
+fn is_operating_mode_set(
+    status: &OperatingModeStatus,
+    mode: Option<OperatingModeSetting>,
+    installer_lock: InstallerLock,
+) -> bool {
+    mode.is_none_or(|mode| status.mode == OperatingMode::from(mode))
+        && (installer_lock == InstallerLock::ParameterNotAvailable
+            || status.installer_lock == installer_lock)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{decoder::Decoder, types::FrameHeader};
+    #[cfg(feature = "experimental")]
+    use crate::{
+        protocol::{BankName, BatteryType, StateOfCharge, StateOfHealth},
+        units::{AmpHours, Percent},
+    };
+
+    /// Builds an acknowledgement from the device at address 0x00, based on the acknowledgement of
+    /// PGN 0xF003 received in dump/dumped_btatt_values.log.
+    pub(crate) fn acknowledgement(ack_type: AcknowledgementType, pgn: u16, data_page: u8) -> Frame {
+        let mut payload = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x03, 0xF0, 0x01];
+        payload[0] = ack_type as u8;
+        payload[5..7].copy_from_slice(&pgn.to_le_bytes());
+        payload[7] = data_page;
+        Frame::build(0x00, 0xFF, pgn::ACKNOWLEDGEMENT, &payload)
+    }
+
+    fn decode(frame: Frame) -> Message {
+        Decoder::new().try_decode(&frame).unwrap()
+    }
+
+    #[test]
+    fn test_request_for_address_claimed() {
+        let frame = Command::Request {
+            pgn: pgn::ADDRESS_CLAIMED,
+            data_page: 0,
+        }
+        .to_frame(0xFD, 0x00);
+        assert_eq!(
+            *frame.0,
+            [0xAA, 0xFD, 0x00, 0x00, 0xEA, 0x03, 0x00, 0xEE, 0x00, 0x28, 0x99]
+        );
+    }
+
+    #[test]
+    fn test_request_round_trip() {
+        let frame = Command::Request {
+            pgn: pgn::DEVICE_NAME,
+            data_page: 1,
+        }
+        .to_frame(0xFD, FrameHeader::BROADCAST_ADDRESS);
+        let message = Decoder::new().try_decode(&frame).unwrap();
+        assert!(message.header.is_broadcast());
+        assert!(matches!(
+            message.body,
+            TbsPg::Request {
+                pgn: pgn::DEVICE_NAME,
+                data_page: 1
+            }
+        ));
+    }
+
+    #[cfg(feature = "experimental")]
+    #[test]
+    fn test_write_basic_setup() {
+        let setup = BasicSetup {
+            bank_enable: BankEnable::Enabled,
+            bank_name: BankName::MainBatteryBank,
+            bank_capacity: BankCapacity::CapacityAh(200),
+            battery_type: BatteryType::AGM,
+        };
+        let command = Command::WriteBasicSetup {
+            bank: BankId::BANK_1,
+            setup,
+        };
+        let frame = command.to_frame(0xFD, 0x00);
+        assert_eq!(frame.pgn(), Some(0xF020));
+        assert_eq!(
+            frame.payload().unwrap(),
+            [0xFD, 0xFF, 0x80, 0x0C, 0xC8, 0x00, 0xFF, 0x03]
+        );
+        match Decoder::new().try_decode(&frame).unwrap().body {
+            TbsPg::BasicSetup { bank, data } => {
+                assert_eq!(bank, BankId::BANK_1);
+                assert_eq!(data, setup);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "experimental")]
+    #[test]
+    fn test_write_basic_setup_unchanged_parameters() {
+        let command = Command::WriteBasicSetup {
+            bank: BankId::BANK_2,
+            setup: BasicSetup {
+                bank_enable: BankEnable::ParameterUnavailable,
+                bank_name: BankName::ParameterNotAvailable,
+                bank_capacity: BankCapacity::CapacityAh(100),
+                battery_type: BatteryType::ParameterNotAvailable,
+            },
+        };
+        assert_eq!(
+            command.to_frame(0xFD, 0x00).payload().unwrap(),
+            [0xFF, 0xFF, 0xFF, 0xFF, 0x64, 0x00, 0xFF, 0xFF]
+        );
+    }
+
+    #[cfg(feature = "experimental")]
+    #[test]
+    fn test_check_response() {
+        let command = Command::WriteBasicSetup {
+            bank: BankId::BANK_1,
+            setup: BasicSetup {
+                bank_enable: BankEnable::Enabled,
+                bank_name: BankName::HouseBatteryBank,
+                bank_capacity: BankCapacity::ParameterNotAvailable,
+                battery_type: BatteryType::LiFePo4,
+            },
+        };
+        assert_eq!(
+            command.check_response(&decode(acknowledgement(
+                AcknowledgementType::PositiveAcknowledgement,
+                0xF020,
+                1
+            ))),
+            Some(Ok(()))
+        );
+        assert_eq!(
+            command.check_response(&decode(acknowledgement(
+                AcknowledgementType::AccessDenied,
+                0xF020,
+                1
+            ))),
+            Some(Err(CommandError::AccessDenied))
+        );
+        assert_eq!(
+            command.check_response(&decode(acknowledgement(
+                AcknowledgementType::PositiveAcknowledgement,
+                0xF02A,
+                1
+            ))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_check_request_response() {
+        let request = Command::Request {
+            pgn: 0xF020,
+            data_page: 1,
+        };
+        // Basic setup of bank 1 as sent by the device in dump/dumped_btatt_values.log.
+        let setup = decode(Frame::build(
+            0x00,
+            0xFF,
+            0xF020,
+            &[0xFD, 0xBF, 0x80, 0x0C, 0xC8, 0x00, 0xFF, 0x03],
+        ));
+        assert_eq!(request.check_response(&setup), Some(Ok(())));
+        assert_eq!(
+            request.check_response(&decode(acknowledgement(
+                AcknowledgementType::CannotRespond,
+                0xF020,
+                1
+            ))),
+            Some(Err(CommandError::CannotRespond))
+        );
+        assert_eq!(
+            request.check_response(&decode(acknowledgement(
+                AcknowledgementType::PositiveAcknowledgement,
+                0xF02A,
+                1
+            ))),
+            None
+        ); // An acknowledgement for the same PGN on another data page does not answer the request.
+        assert_eq!(
+            request.check_response(&decode(acknowledgement(
+                AcknowledgementType::CannotRespond,
+                0xF020,
+                0
+            ))),
+            None
+        );
+    }
+
+    #[cfg(feature = "experimental")]
+    #[test]
+    fn test_set_operating_mode() {
+        let frame = Command::switch_off().to_frame(0xFD, 0x00);
+        assert_eq!(frame.pgn(), Some(pgn::OPERATING_MODE));
+        // As sent by the device in dump/dumped_btatt_values.log, with the installer lock unset.
+        assert_eq!(
+            frame.payload().unwrap(),
+            [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+        assert_eq!(
+            Command::installer_lock(true)
+                .to_frame(0xFD, 0x00)
+                .payload()
+                .unwrap(),
+            [0xFF, 0xFF, 0xFF, 0x7F, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+    }
+
+    #[cfg(feature = "experimental")]
+    #[test]
+    fn test_check_operating_mode_response() {
+        let status = |mode: OperatingModeSetting, installer_lock: InstallerLock| {
+            Decoder::new()
+                .try_decode(
+                    &Command::SetOperatingMode {
+                        mode: Some(mode),
+                        installer_lock,
+                    }
+                    .to_frame(0x00, 0xFF),
+                )
+                .unwrap()
+        };
+        let command = Command::night_mode();
+        assert_eq!(
+            command.check_response(&status(
+                OperatingModeSetting::DeviceOnNightMode,
+                InstallerLock::InstallerLockOn
+            )),
+            Some(Ok(()))
+        );
+        assert_eq!(
+            command.check_response(&status(
+                OperatingModeSetting::DeviceOn,
+                InstallerLock::InstallerLockOn
+            )),
+            None
+        );
+        assert_eq!(
+            command.check_response(&decode(acknowledgement(
+                AcknowledgementType::AccessDenied,
+                pgn::OPERATING_MODE,
+                1
+            ))),
+            Some(Err(CommandError::AccessDenied))
+        );
+
+        assert_eq!(
+            command.check_response(&decode(acknowledgement(
+                AcknowledgementType::PositiveAcknowledgement,
+                pgn::OPERATING_MODE,
+                1
+            ))),
+            None
+        );
+
+        let command = Command::installer_lock(false);
+        assert_eq!(
+            command.check_response(&status(
+                OperatingModeSetting::DeviceOff,
+                InstallerLock::InstallerLockOff
+            )),
+            Some(Ok(()))
+        );
+    }
+
+    #[cfg(feature = "experimental")]
+    #[test]
+    fn test_reset_commands() {
+        let decoder = Decoder::new();
+        let bank_2 = BankId::BANK_2;
+
+        let frame = Command::ResetConsumedAmpHours { bank: bank_2 }.to_frame(0xFD, 0x00);
+        assert_eq!(frame.pgn(), Some(0xF023));
+        match decoder.try_decode(&frame).unwrap().body {
+            TbsPg::PowerAndCharge { bank, data } => {
+                assert_eq!(bank, bank_2);
+                assert_eq!(data.power, None);
+                assert_eq!(
+                    data.consumed_amp_hours,
+                    Some(AmpHours::from_centiamp_hours(0))
+                );
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let frame = Command::SynchronizeStateOfCharge { bank: bank_2 }.to_frame(0xFD, 0x00);
+        assert_eq!(frame.pgn(), Some(0xF024));
+        match decoder.try_decode(&frame).unwrap().body {
+            TbsPg::BankStatus { data, .. } => {
+                assert!(matches!(
+                    data.state_of_charge,
+                    StateOfCharge::ChargePercentage(percent) if percent == Percent::from_hundredths(10000)
+                ));
+                assert!(matches!(data.state_of_health, StateOfHealth::Unavailable));
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let command = Command::ResetStateOfHealth { bank: bank_2 };
+        match decoder
+            .try_decode(&command.to_frame(0xFD, 0x00))
+            .unwrap()
+            .body
+        {
+            TbsPg::BankStatus { data, .. } => {
+                assert!(matches!(data.state_of_charge, StateOfCharge::Unavailable));
+                assert!(matches!(
+                    data.state_of_health,
+                    StateOfHealth::HealthPercentage(percent) if percent == Percent::from_hundredths(10000)
+                ));
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+        assert_eq!(
+            command.check_response(&decode(acknowledgement(
+                AcknowledgementType::PositiveAcknowledgement,
+                0xF024,
+                1
+            ))),
+            Some(Ok(()))
+        );
+        assert_eq!(
+            command.check_response(&decode(acknowledgement(
+                AcknowledgementType::NegativeAcknowledgement,
+                0xF024,
+                1
+            ))),
+            Some(Err(CommandError::NegativeAcknowledgement))
+        );
+    }
+}
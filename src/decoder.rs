@@ -22,50 +22,109 @@
 
 use crate::{
     protocol::{
-        Acknowledgement, AcknowledgementType, AddressClaimed, BankCapacity, BankEnable, BankName,
-        BankStatus, BasicQuantities, BasicSetup, BatteryType, BrandId, ChargeStage, ChargeState,
-        DeviceId, DeviceName, IndicatorState, InstallerLock, OperatingMode, OperatingModeStatus,
-        PowerAndCharge, RemainingTime, StateOfCharge, StateOfHealth, TbsPg, Temperature, Version,
-        VersionInfo,
+        pgn, Acknowledgement, AcknowledgementType, AddressClaimName, AddressClaimed, BankCapacity,
+        BankEnable, BankId, BankMessageKind, BankName, BankStatus, BasicQuantities, BasicSetup,
+        BatteryType, BrandId, ChargeStage, ChargeState, DeviceId, DeviceName, IndicatorState,
+        InstallerLock, Message, OperatingMode, OperatingModeStatus, PowerAndCharge, RawMessage,
+        RegisterDump, RemainingTime, StateOfCharge, StateOfHealth, StatusFlags, TbsPg, Temperature,
+        Version, VersionInfo,
     },
-    types::Frame,
+    types::{self, Frame, FrameHeader, FrameRef},
+    units::{AmpHours, Amperes, Celsius, Percent, Volts, Watts},
 };
 
+use std::{collections::HashMap, fmt};
 use tracing::error;
 
-pub struct Decoder {}
-
-type PgnTag = [u8; 2];
-
-const PGN_TAG_BB1DC: (PgnTag, usize) = ([0x18, 0xF0], 16);
-const PGN_TAG_BB1PC: (PgnTag, usize) = ([0x19, 0xF0], 16);
-const PGN_TAG_BB1ST: (PgnTag, usize) = ([0x1A, 0xF0], 16);
-const PGN_TAG_BB1CS: (PgnTag, usize) = ([0x1E, 0xF0], 16);
-const PGN_TAG_BB1BS: (PgnTag, usize) = ([0x20, 0xF0], 16);
-const PGN_TAG_BB2DC: (PgnTag, usize) = ([0x22, 0xF0], 16);
-const PGN_TAG_BB2PC: (PgnTag, usize) = ([0x23, 0xF0], 16);
-const PGN_TAG_BB2ST: (PgnTag, usize) = ([0x24, 0xF0], 16);
-const PGN_TAG_BB2CS: (PgnTag, usize) = ([0x28, 0xF0], 16);
-const PGN_TAG_BB2BS: (PgnTag, usize) = ([0x2A, 0xF0], 16);
-const PGN_TAG_BB3DC: (PgnTag, usize) = ([0x2C, 0xF0], 16);
-const PGN_TAG_BB3PC: (PgnTag, usize) = ([0x2D, 0xF0], 16);
-const PGN_TAG_BB3ST: (PgnTag, usize) = ([0x2E, 0xF0], 16);
-const PGN_TAG_BB3CS: (PgnTag, usize) = ([0x32, 0xF0], 16);
-const PGN_TAG_BB3BS: (PgnTag, usize) = ([0x34, 0xF0], 16);
-
-const PGN_TAG_ADDRESS_CLAIMED: (PgnTag, usize) = ([0x00, 0xEE], 16);
-const PGN_TAG_VERSION_INFO: (PgnTag, usize) = ([0x02, 0xF0], 16);
-const PGN_TAG_HEARTBEAT: (PgnTag, usize) = ([0xFF, 0xFF], 8);
-const PGN_TAG_ACKNOWLEDGEMENT: (PgnTag, usize) = ([0x00, 0xe8], 16);
-const PGN_TAG_DEVICE_NAME: (PgnTag, usize) = ([0x00, 0xF0], 40);
-const PGN_TAG_OPERATION_MODE: (PgnTag, usize) = ([0x0E, 0xF0], 16);
-
-enum BankId {
-    Bank1,
-    Bank2,
-    Bank3,
+pub struct Decoder {
+    bank_count: u8,
+    registered: HashMap<(Pgn, Option<usize>), Box<dyn PgnDecoder>>,
 }
 
+/// A decoder for a single PGN, registered with [`Decoder::register`] to decode PGNs that are
+/// unknown to laad, or to replace the built-in decoding of a PGN.
+///
+/// Implemented for closures taking the frame header and the payload, so that
+/// `|header: &FrameHeader, payload: &[u8]| Ok(TbsPg::Custom(CustomMessage::new(...)))` can be
+/// registered directly.
+pub trait PgnDecoder: Send + Sync {
+    /// Decodes the payload of a frame with a valid checksum and declared length.
+    fn decode(&self, header: &FrameHeader, payload: &[u8]) -> Result<TbsPg, DecodeError>;
+}
+
+impl<F> PgnDecoder for F
+where
+    F: Fn(&FrameHeader, &[u8]) -> Result<TbsPg, DecodeError> + Send + Sync,
+{
+    fn decode(&self, header: &FrameHeader, payload: &[u8]) -> Result<TbsPg, DecodeError> {
+        self(header, payload)
+    }
+}
+
+/// Reasons why a frame could not be decoded, returned by [`Decoder::try_decode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The frame is shorter than the minimum frame length of 8 bytes.
+    TooShort { len: usize },
+    /// The checksum in the frame does not match the checksum calculated over the frame.
+    ChecksumMismatch { expected: u8, actual: u8, pgn: u16 },
+    /// The length byte of the frame declares a payload length that differs from the number of
+    /// payload bytes in the frame.
+    DeclaredLengthMismatch {
+        pgn: u16,
+        declared: usize,
+        actual: usize,
+    },
+    /// The PGN is known, but the payload is shorter than the fields expected for it.
+    LengthMismatch {
+        pgn: u16,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::TooShort { len } => write!(f, "Frame too short: {} bytes", len),
+            DecodeError::ChecksumMismatch {
+                expected,
+                actual,
+                pgn,
+            } => write!(
+                f,
+                "Checksum not valid for PGN 0x{:04X}: 0x{:02X} vs 0x{:02X}",
+                pgn, actual, expected
+            ),
+            DecodeError::DeclaredLengthMismatch {
+                pgn,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "Payload length for PGN 0x{:04X} declared as {} bytes, but frame has {}",
+                pgn, declared, actual
+            ),
+            DecodeError::LengthMismatch {
+                pgn,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Payload too short for PGN 0x{:04X}: {} bytes, expected at least {}",
+                pgn, actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+type Pgn = u16;
+
+/// Number of battery banks decoded by default, as supported by the Expert Modular.
+const DEFAULT_BANK_COUNT: u8 = 3;
+
 impl BankName {
     fn from_u8(byte: u8) -> Self {
         match byte {
@@ -99,6 +158,24 @@ impl BankName {
     }
 }
 
+impl DeviceId {
+    fn from_u16(device_id: u16) -> Self {
+        match device_id {
+            0x0A24 => DeviceId::ExpertModular,
+            _ => DeviceId::Other(device_id),
+        }
+    }
+}
+
+impl BrandId {
+    fn from_u8(brand_id: u8) -> Self {
+        match brand_id {
+            0x32 => BrandId::TbsElectronics,
+            _ => BrandId::Other(brand_id),
+        }
+    }
+}
+
 impl ChargeStage {
     fn from_u8(charge_stage: u8) -> Self {
         match charge_stage {
@@ -165,107 +242,263 @@ impl Default for Decoder {
 
 impl Decoder {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            bank_count: DEFAULT_BANK_COUNT,
+            registered: HashMap::new(),
+        }
+    }
+
+    /// Creates a decoder that decodes the per-bank messages of `bank_count` banks, for systems with
+    /// more than the three banks decoded by default.
+    ///
+    /// Only available with the `experimental` feature: the PGNs of banks after the third are
+    /// extrapolated from the first three banks and have not been verified against a device, see
+    /// [`BankId`]. With more banks, PGNs such as 0xF03E are decoded as per-bank messages rather
+    /// than as [`TbsPg::Raw`], even if the device uses them for other messages.
+    #[cfg(feature = "experimental")]
+    pub fn with_bank_count(bank_count: u8) -> Self {
+        Self {
+            bank_count,
+            registered: HashMap::new(),
+        }
+    }
+
+    /// Registers a decoder for frames with the given PGN. If `payload_len` is given, the decoder
+    /// is only used for payloads of that length, otherwise for payloads of any length. Decoders
+    /// for a specific length take precedence over decoders for any length.
+    ///
+    /// Registered decoders take precedence over the built-in decoding of a PGN. Registering a
+    /// decoder for the same PGN and length again replaces the earlier one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use laad::{
+    ///     decoder::Decoder,
+    ///     protocol::{CustomMessage, TbsPg},
+    ///     types::{Frame, FrameHeader},
+    /// };
+    ///
+    /// #[derive(Debug)]
+    /// struct VendorCounter(u16);
+    ///
+    /// let mut decoder = Decoder::new();
+    /// decoder.register(0xF0F0, Some(2), |_: &FrameHeader, payload: &[u8]| {
+    ///     let counter = u16::from_le_bytes([payload[0], payload[1]]);
+    ///     Ok(TbsPg::Custom(CustomMessage::new(VendorCounter(counter))))
+    /// });
+    ///
+    /// let message = decoder
+    ///     .try_decode(&Frame::build(0x00, 0xFF, 0xF0F0, &[0x2A, 0x00]))
+    ///     .unwrap();
+    /// let TbsPg::Custom(custom) = message.body else {
+    ///     panic!("Expected a custom message");
+    /// };
+    /// assert_eq!(custom.downcast_ref::<VendorCounter>().unwrap().0, 42);
+    /// ```
+    pub fn register<D: PgnDecoder + 'static>(
+        &mut self,
+        pgn: u16,
+        payload_len: Option<usize>,
+        decoder: D,
+    ) -> &mut Self {
+        self.registered
+            .insert((pgn, payload_len), Box::new(decoder));
+        self
+    }
+
+    /// Returns the registered decoder for a PGN and payload length, if any.
+    fn registered_decoder(&self, pgn: Pgn, payload_len: usize) -> Option<&dyn PgnDecoder> {
+        self.registered
+            .get(&(pgn, Some(payload_len)))
+            .or_else(|| self.registered.get(&(pgn, None)))
+            .map(|decoder| decoder.as_ref())
+    }
+
+    /// Returns the bank and message kind for PGNs of per-bank messages of the decoded banks.
+    fn bank_message(&self, pgn: Pgn) -> Option<(BankId, BankMessageKind)> {
+        BankId::from_pgn(pgn).filter(|(bank, _)| bank.number() <= self.bank_count)
     }
+
+    /// Returns the number of payload bytes that need to be present to decode a known PGN, or `None`
+    /// for unknown PGNs. Longer payloads are accepted, bytes beyond the known fields are ignored.
+    fn min_payload_len(&self, pgn: Pgn) -> Option<usize> {
+        match pgn {
+            pgn::HEARTBEAT => Some(0),
+            pgn::DEVICE_NAME => Some(32),
+            pgn::REGISTER_DUMP => Some(RegisterDump::LEN),
+            pgn::REQUEST => Some(3),
+            pgn::ADDRESS_CLAIMED
+            | pgn::VERSION_INFO
+            | pgn::ACKNOWLEDGEMENT
+            | pgn::OPERATING_MODE => Some(8),
+            _ => self.bank_message(pgn).map(|_| 8),
+        }
+    }
+
     /// Decodes a given frame into a `TbsPg` type.
     ///
-    /// Receives frames with bytestuffing reverted and:
-    /// 1. Ensures the frame is at least 8 bytes long.
-    /// 2. Extracts the PGN tag and checksum, and validates it.
-    /// 4. Matches the PGN tag and frame length for known PGNs and decodes them.
+    /// Convenience wrapper around [`try_decode`](Decoder::try_decode) that logs decoding errors.
     ///
     /// # Parameters
     /// - `frame`: The frame to be decoded, with bytestuffing reverted.
     ///
     /// # Returns
-    /// - `TbsPg`: The decoded frame as a `TbsPg` type. If the frame is invalid or the PGN tag is unknown, it returns `TbsPg::Unknown`.
+    /// - `TbsPg`: The decoded frame as a `TbsPg` type. If the frame is invalid, it returns `TbsPg::Unknown`.
     ///
     /// # Errors
-    /// - Logs an error if the checksum calculation fails or if the PGN tag is unknown.
+    /// - Logs an error if the frame could not be decoded.
     pub fn decode_frame(&self, frame: Frame) -> TbsPg {
-        let frame_len = frame.0.len();
-        if frame_len < 8 {
-            return TbsPg::Unknown;
+        match self.try_decode(&frame) {
+            Ok(message) => message.body,
+            Err(err) => {
+                error!("{}", err);
+                TbsPg::Unknown
+            }
         }
+    }
 
-        let pgn_tag = [frame.0[3], frame.0[4]];
-        let checksum = frame.0[frame.0.len() - 2];
-        let calculated_checksum = self.calculate_checksum(&frame);
+    /// Decodes a given frame into a [`Message`], with the frame header and the decoded `TbsPg`.
+    ///
+    /// Receives frames with bytestuffing reverted and:
+    /// 1. Ensures the frame is at least 8 bytes long.
+    /// 2. Extracts the PGN and checksum, and validates it.
+    /// 3. Validates the payload length declared in the frame header against the frame length.
+    /// 4. Decodes the payload with a [registered](Decoder::register) decoder for the PGN, if any.
+    /// 5. Matches the PGN for known PGNs and decodes them, if the payload is long enough.
+    /// 6. Returns messages with unknown PGNs as [`TbsPg::Raw`], with the payload kept.
+    ///
+    /// # Parameters
+    /// - `frame`: The frame to be decoded, with bytestuffing reverted.
+    ///
+    /// # Errors
+    /// - [`DecodeError::TooShort`] if the frame is shorter than 8 bytes.
+    /// - [`DecodeError::ChecksumMismatch`] if the checksum in the frame is not valid.
+    /// - [`DecodeError::DeclaredLengthMismatch`] if the declared payload length does not match the frame.
+    /// - [`DecodeError::LengthMismatch`] if the PGN is known, but the payload is too short for it.
+    pub fn try_decode(&self, frame: &Frame) -> Result<Message, DecodeError> {
+        self.decode(&frame.0)
+    }
 
-        if let Some(calculated_checksum) = calculated_checksum {
-            if checksum != calculated_checksum {
-                error!(
-                    "Checksum not valid for PGN tag: {:02X?}, 0x{:02X?} vs 0x{:02X}?",
-                    pgn_tag, checksum, calculated_checksum
-                );
-                return TbsPg::Unknown;
-            }
-        } else {
-            error!("Failed to calculate checksum for PGN tag: {:02X?}", pgn_tag);
-            return TbsPg::Unknown;
-        }
-
-        match (pgn_tag, frame_len) {
-            PGN_TAG_BB1ST => self.decode_bbst(BankId::Bank1, frame),
-            PGN_TAG_BB2ST => self.decode_bbst(BankId::Bank2, frame),
-            PGN_TAG_BB3ST => self.decode_bbst(BankId::Bank3, frame),
-            PGN_TAG_VERSION_INFO => self.decode_version_info(frame),
-            PGN_TAG_HEARTBEAT => TbsPg::Heartbeat,
-            PGN_TAG_BB1DC => self.decode_bbdc(BankId::Bank1, frame),
-            PGN_TAG_BB2DC => self.decode_bbdc(BankId::Bank2, frame),
-            PGN_TAG_BB3DC => self.decode_bbdc(BankId::Bank3, frame),
-            PGN_TAG_BB1PC => self.decode_bbpc(BankId::Bank1, frame),
-            PGN_TAG_BB2PC => self.decode_bbpc(BankId::Bank2, frame),
-            PGN_TAG_BB3PC => self.decode_bbpc(BankId::Bank3, frame),
-            PGN_TAG_BB1CS => self.decode_bbcs(BankId::Bank1, &frame),
-            PGN_TAG_BB2CS => self.decode_bbcs(BankId::Bank2, &frame),
-            PGN_TAG_BB3CS => self.decode_bbcs(BankId::Bank3, &frame),
-            PGN_TAG_BB1BS => self.decode_bbbs(BankId::Bank1, frame),
-            PGN_TAG_BB2BS => self.decode_bbbs(BankId::Bank2, frame),
-            PGN_TAG_BB3BS => self.decode_bbbs(BankId::Bank3, frame),
-            PGN_TAG_ADDRESS_CLAIMED => self.decode_address_claimed(frame),
-            PGN_TAG_ACKNOWLEDGEMENT => self.decode_acknowledgement(frame),
-            PGN_TAG_DEVICE_NAME => self.decode_device_name(frame),
-            PGN_TAG_OPERATION_MODE => self.decode_operating_mode(frame),
-            _ => {
-                error!(
-                    "Unknown PGN tag: {:02X?}, frame length {:?}",
-                    pgn_tag, frame_len
-                );
-                TbsPg::Unknown
+    /// Decodes a frame borrowed from a buffer into a [`Message`], without copying the frame.
+    /// See [`try_decode`](Decoder::try_decode) for the validation steps and errors.
+    ///
+    /// # Parameters
+    /// - `frame`: The bytes of the frame to be decoded, with bytestuffing reverted, for example a
+    ///   [`FrameRef`] returned by [`FrameParser::next_frame_ref`](crate::frameparser::FrameParser::next_frame_ref).
+    pub fn decode(&self, frame: &[u8]) -> Result<Message, DecodeError> {
+        let frame_len = frame.len();
+        let (Some(header), Some(calculated_checksum)) =
+            (FrameRef(frame).header(), self.calculate_checksum(frame))
+        else {
+            return Err(DecodeError::TooShort { len: frame_len });
+        };
+
+        let pgn = header.pgn;
+        let checksum = frame[frame_len - 2];
+        if checksum != calculated_checksum {
+            return Err(DecodeError::ChecksumMismatch {
+                expected: calculated_checksum,
+                actual: checksum,
+                pgn,
+            });
+        }
+
+        let payload_len = frame_len - Frame::MIN_LEN;
+        if header.length as usize != payload_len {
+            return Err(DecodeError::DeclaredLengthMismatch {
+                pgn,
+                declared: header.length as usize,
+                actual: payload_len,
+            });
+        }
+        if let Some(registered) = self.registered_decoder(pgn, payload_len) {
+            return Ok(Message {
+                header,
+                body: registered.decode(&header, &frame[6..frame_len - 2])?,
+            });
+        }
+        if let Some(expected) = self.min_payload_len(pgn) {
+            if payload_len < expected {
+                return Err(DecodeError::LengthMismatch {
+                    pgn,
+                    expected,
+                    actual: payload_len,
+                });
             }
         }
+
+        let decoded = match pgn {
+            pgn::VERSION_INFO => self.decode_version_info(frame),
+            pgn::HEARTBEAT => TbsPg::Heartbeat,
+            pgn::ADDRESS_CLAIMED => self.decode_address_claimed(frame),
+            pgn::ACKNOWLEDGEMENT => self.decode_acknowledgement(frame),
+            pgn::DEVICE_NAME => self.decode_device_name(frame),
+            pgn::OPERATING_MODE => self.decode_operating_mode(frame),
+            pgn::REGISTER_DUMP => self.decode_register_dump(frame),
+            pgn::REQUEST => TbsPg::Request {
+                pgn: u16::from_le_bytes([frame[6], frame[7]]),
+                data_page: frame[8],
+            },
+            _ => match self.bank_message(pgn) {
+                Some((bank, BankMessageKind::BasicQuantities)) => TbsPg::BasicQuantities {
+                    bank,
+                    data: self.decode_bbdc(frame),
+                },
+                Some((bank, BankMessageKind::PowerAndCharge)) => TbsPg::PowerAndCharge {
+                    bank,
+                    data: self.decode_bbpc(frame),
+                },
+                Some((bank, BankMessageKind::BankStatus)) => TbsPg::BankStatus {
+                    bank,
+                    data: self.decode_bbst(frame),
+                },
+                Some((bank, BankMessageKind::ChargeState)) => TbsPg::ChargeState {
+                    bank,
+                    data: self.decode_bbcs(frame),
+                },
+                Some((bank, BankMessageKind::BasicSetup)) => TbsPg::BasicSetup {
+                    bank,
+                    data: self.decode_bbbs(frame),
+                },
+                None => TbsPg::Raw(RawMessage {
+                    pgn,
+                    payload: frame[6..frame_len - 2].to_vec(),
+                }),
+            },
+        };
+        Ok(Message {
+            header,
+            body: decoded,
+        })
     }
 
-    fn calculate_checksum(&self, frame: &Frame) -> Option<u8> {
-        if frame.0.len() < 8 {
+    fn calculate_checksum(&self, frame: &[u8]) -> Option<u8> {
+        if frame.len() < Frame::MIN_LEN {
             return None;
         }
-        let sum: u8 = frame.0[1..frame.0.len() - 2]
-            .iter()
-            .fold(0, |acc, &b| acc.wrapping_add(b));
-        Some(sum.wrapping_neg())
+        Some(types::calculate_checksum(&frame[1..frame.len() - 2]))
     }
 
-    fn decode_bbst(&self, bank_id: BankId, frame: Frame) -> TbsPg {
-        let _flags = u16::from_le_bytes([frame.0[6], frame.0[7]]);
-        let soc = u16::from_le_bytes([frame.0[8], frame.0[9]]);
+    fn decode_bbst(&self, frame: &[u8]) -> BankStatus {
+        let flags = StatusFlags(u16::from_le_bytes([frame[6], frame[7]]));
+        let soc = u16::from_le_bytes([frame[8], frame[9]]);
         let soc = if soc == 65535 {
             StateOfCharge::Unavailable
         } else if soc == 65533 {
             StateOfCharge::Initializing
         } else {
-            StateOfCharge::ChargePercentage(soc as f32 / 100.0)
+            StateOfCharge::ChargePercentage(Percent::from_hundredths(soc))
         };
-        let soh = u16::from_le_bytes([frame.0[10], frame.0[11]]);
+        let soh = u16::from_le_bytes([frame[10], frame[11]]);
         let soh = if soh == 65535 {
             StateOfHealth::Unavailable
         } else if soh == 65533 {
             StateOfHealth::Initializing
         } else {
-            StateOfHealth::HealthPercentage(soh as f32 / 100.0)
+            StateOfHealth::HealthPercentage(Percent::from_hundredths(soh))
         };
-        let time_remaining = u16::from_le_bytes([frame.0[12], frame.0[13]]);
+        let time_remaining = u16::from_le_bytes([frame[12], frame[13]]);
         let time_remaining = if time_remaining == 65535 {
             RemainingTime::Unavailable
         } else if time_remaining == 65533 {
@@ -273,70 +506,58 @@ impl Decoder {
         } else {
             RemainingTime::Minutes(time_remaining)
         };
-        let bank_status = BankStatus {
+        BankStatus {
+            flags,
             state_of_charge: soc,
             state_of_health: soh,
             time_remaining,
-        };
-        match bank_id {
-            BankId::Bank1 => TbsPg::Bb1st(bank_status),
-            BankId::Bank2 => TbsPg::Bb2st(bank_status),
-            BankId::Bank3 => TbsPg::Bb3st(bank_status),
         }
     }
 
-    fn decode_bbcs(&self, bank_id: BankId, frame: &Frame) -> TbsPg {
-        let _flags = u16::from_le_bytes([frame.0[6], frame.0[7]]);
-        let charge_stage = frame.0[8];
+    fn decode_bbcs(&self, frame: &[u8]) -> ChargeState {
+        let flags = StatusFlags(u16::from_le_bytes([frame[6], frame[7]]));
+        let charge_stage = frame[8];
         let charge_stage = ChargeStage::from_u8(charge_stage);
-        let indicator_flags = frame.0[9];
+        let indicator_flags = frame[9];
         let indicator_0_49 = IndicatorState::from_u8(indicator_flags & 0b11);
         let indicator_50_79 = IndicatorState::from_u8((indicator_flags >> 2) & 0b11);
         let indicator_80_99 = IndicatorState::from_u8((indicator_flags >> 4) & 0b11);
         let indicator_100 = IndicatorState::from_u8((indicator_flags >> 6) & 0b11);
-        let charge_state = ChargeState {
+        ChargeState {
+            flags,
             stage: charge_stage,
             indicator_0_49,
             indicator_50_79,
             indicator_80_99,
             indicator_100,
-        };
-
-        match bank_id {
-            BankId::Bank1 => TbsPg::Bb1cs(charge_state),
-            BankId::Bank2 => TbsPg::Bb2cs(charge_state),
-            BankId::Bank3 => TbsPg::Bb3cs(charge_state),
         }
     }
 
-    fn decode_bbpc(&self, bank_id: BankId, frame: Frame) -> TbsPg {
-        let _flags = u16::from_le_bytes([frame.0[6], frame.0[7]]);
+    fn decode_bbpc(&self, frame: &[u8]) -> PowerAndCharge {
+        let flags = StatusFlags(u16::from_le_bytes([frame[6], frame[7]]));
         const UNAVAILABLE: u32 = 0x00FFFFFF;
-        let power = u32::from_be_bytes([frame.0[8], frame.0[9], frame.0[10], 0]);
+        // Both are sent with an offset of 8000000 steps, -800000 W and -80000 Ah.
+        let power = u32::from_le_bytes([frame[8], frame[9], frame[10], 0]);
         let power = if power != UNAVAILABLE {
-            Some(power as f32 * 0.1 - 80000.0)
+            Some(Watts::from_deciwatts(power as i32 - 8_000_000))
         } else {
             None
         };
-        let charge = u32::from_be_bytes([frame.0[11], frame.0[12], frame.0[13], 0]);
+        let charge = u32::from_le_bytes([frame[11], frame[12], frame[13], 0]);
         let charge = if charge != UNAVAILABLE {
-            Some(charge as f32 * 0.01 - 80000.0)
+            Some(AmpHours::from_centiamp_hours(charge as i32 - 8_000_000))
         } else {
             None
         };
 
-        let power_and_charge = PowerAndCharge {
+        PowerAndCharge {
+            flags,
             power,
             consumed_amp_hours: charge,
-        };
-        match bank_id {
-            BankId::Bank1 => TbsPg::Bb1pc(power_and_charge),
-            BankId::Bank2 => TbsPg::Bb2pc(power_and_charge),
-            BankId::Bank3 => TbsPg::Bb3pc(power_and_charge),
         }
     }
 
-    fn decode_version_info(&self, frame: Frame) -> TbsPg {
+    fn decode_version_info(&self, frame: &[u8]) -> TbsPg {
         fn convert_version(version: u16) -> Version {
             Version {
                 major: (version / 100) as u32,
@@ -344,10 +565,10 @@ impl Decoder {
                 maintenance: (version % 10) as u8,
             }
         }
-        let firmware_version = u16::from_le_bytes([frame.0[6], frame.0[7]]);
-        let hardware_version = u16::from_le_bytes([frame.0[8], frame.0[9]]);
-        let bootloader_version = u16::from_le_bytes([frame.0[8], frame.0[9]]);
-        let auxiliary_version = u16::from_le_bytes([frame.0[10], frame.0[11]]);
+        let firmware_version = u16::from_le_bytes([frame[6], frame[7]]);
+        let hardware_version = u16::from_le_bytes([frame[8], frame[9]]);
+        let bootloader_version = u16::from_le_bytes([frame[8], frame[9]]);
+        let auxiliary_version = u16::from_le_bytes([frame[10], frame[11]]);
 
         TbsPg::VersionInfo(VersionInfo {
             firmware_version: convert_version(firmware_version),
@@ -357,49 +578,45 @@ impl Decoder {
         })
     }
 
-    fn decode_bbdc(&self, bank: BankId, frame: Frame) -> TbsPg {
-        let _flags = u16::from_le_bytes([frame.0[6], frame.0[7]]);
-        let voltage = u16::from_le_bytes([frame.0[8], frame.0[9]]);
+    fn decode_bbdc(&self, frame: &[u8]) -> BasicQuantities {
+        let flags = StatusFlags(u16::from_le_bytes([frame[6], frame[7]]));
+        let voltage = u16::from_le_bytes([frame[8], frame[9]]);
         let voltage = if voltage == 0xFFFF {
             None
         } else {
-            Some(voltage as f32 * 0.01)
+            Some(Volts::from_centivolts(voltage as i32))
         };
-        let current = if frame.0[10..13] == [0xFF, 0xFF, 0xFF] {
+        let current = if frame[10..13] == [0xFF, 0xFF, 0xFF] {
             None
         } else {
-            Some(
-                u32::from_le_bytes([frame.0[10], frame.0[11], frame.0[12], 0]) as f32 * 0.01
-                    - 80000.0,
-            )
+            // Sent with an offset of -80000 A.
+            let current = u32::from_le_bytes([frame[10], frame[11], frame[12], 0]);
+            Some(Amperes::from_centiamperes(current as i32 - 8_000_000))
         };
-        let temperature = if frame.0[13] == 0xFE {
+        let temperature = if frame[13] == 0xFE {
             Temperature::NoSensorDetected
-        } else if frame.0[13] == 0xFF {
+        } else if frame[13] == 0xFF {
             Temperature::Unavailable
         } else {
-            Temperature::DegreesCelsius(frame.0[13] as f32 * 0.5 - 40.0)
+            // Sent with an offset of -40 °C.
+            Temperature::DegreesCelsius(Celsius::from_half_degrees(frame[13] as i16 - 80))
         };
-        let quantities = BasicQuantities {
+        BasicQuantities {
+            flags,
             voltage,
             current,
             temperature,
-        };
-        match bank {
-            BankId::Bank1 => TbsPg::Bb1dc(quantities),
-            BankId::Bank2 => TbsPg::Bb2dc(quantities),
-            BankId::Bank3 => TbsPg::Bb3dc(quantities),
         }
     }
 
-    fn decode_bbbs(&self, bank: BankId, frame: Frame) -> TbsPg {
-        let flags = u16::from_le_bytes([frame.0[6], frame.0[7]]);
+    fn decode_bbbs(&self, frame: &[u8]) -> BasicSetup {
+        let flags = u16::from_le_bytes([frame[6], frame[7]]);
         let bank_enable = match flags & 0b11 {
             0 => BankEnable::Disabled,
             1 => BankEnable::Enabled,
             _ => BankEnable::ParameterUnavailable,
         };
-        let battery_type = u16::from_le_bytes([frame.0[8], frame.0[9]]);
+        let battery_type = u16::from_le_bytes([frame[8], frame[9]]);
         let battery_type = match battery_type {
             2000 => BatteryType::Flooded,
             3000 => BatteryType::Gel,
@@ -407,64 +624,61 @@ impl Decoder {
             5000 => BatteryType::LiFePo4,
             _ => BatteryType::ParameterNotAvailable,
         };
-        let bank_capacity = u16::from_le_bytes([frame.0[10], frame.0[11]]);
+        let bank_capacity = u16::from_le_bytes([frame[10], frame[11]]);
         let bank_capacity = if bank_capacity == 0xFFFF {
             BankCapacity::ParameterNotAvailable
         } else {
             BankCapacity::CapacityAh(bank_capacity)
         };
-        let bank_name = BankName::from_u8(frame.0[13]);
-        let basic_setup = BasicSetup {
+        let bank_name = BankName::from_u8(frame[13]);
+        BasicSetup {
             bank_enable,
             bank_name,
             bank_capacity,
             battery_type,
-        };
-        match bank {
-            BankId::Bank1 => TbsPg::Bb1bs(basic_setup),
-            BankId::Bank2 => TbsPg::Bb2bs(basic_setup),
-            BankId::Bank3 => TbsPg::Bb3bs(basic_setup),
         }
     }
 
-    fn decode_address_claimed(&self, frame: Frame) -> TbsPg {
-        let device_id = u16::from_le_bytes([frame.0[12], frame.0[13]]);
-        let device_id = if device_id != 0x0A24 {
-            error!("Unknown device ID: {:04X}", device_id);
-            DeviceId::Unknown
-        } else {
-            DeviceId::ExpertModular
-        };
-        let brand_id = frame.0[11];
-        let brand_id = if brand_id != 0x32 {
-            error!("Unknown brand ID: {:02X}", brand_id);
-            BrandId::Unknown
-        } else {
-            BrandId::TbsElectronics
-        };
-        let serial = u32::from_le_bytes([frame.0[6], frame.0[7], frame.0[8], frame.0[9]]);
+    fn decode_address_claimed(&self, frame: &[u8]) -> TbsPg {
+        let mut name = [0; 8];
+        name.copy_from_slice(&frame[6..14]);
+        let device_id = DeviceId::from_u16(u16::from_le_bytes([frame[12], frame[13]]));
+        let brand_id = BrandId::from_u8(frame[11]);
+        let serial = u32::from_le_bytes([frame[6], frame[7], frame[8], frame[9]]);
         TbsPg::AddressClaimed(AddressClaimed {
             device_id,
             brand_id,
             serial_number: serial.wrapping_neg(),
+            name: AddressClaimName(u64::from_le_bytes(name)),
         })
     }
 
-    fn decode_acknowledgement(&self, frame: Frame) -> TbsPg {
-        let ack_type = AcknowledgementType::from_u8(frame.0[6]);
-        let pgn = u16::from_le_bytes([frame.0[12], frame.0[13]]);
-        TbsPg::Acknowledgement(Acknowledgement { ack_type, pgn })
+    fn decode_acknowledgement(&self, frame: &[u8]) -> TbsPg {
+        let ack_type = AcknowledgementType::from_u8(frame[6]);
+        // Followed by the address the acknowledgement is for and the three bytes of the PGN.
+        let pgn = u16::from_le_bytes([frame[11], frame[12]]);
+        TbsPg::Acknowledgement(Acknowledgement {
+            ack_type,
+            pgn,
+            data_page: frame[13],
+        })
     }
 
-    fn decode_device_name(&self, frame: Frame) -> TbsPg {
+    fn decode_device_name(&self, frame: &[u8]) -> TbsPg {
         let mut name = [0; 32];
-        name.copy_from_slice(&frame.0[6..38]);
+        name.copy_from_slice(&frame[6..38]);
         TbsPg::DeviceName(DeviceName { name })
     }
 
-    fn decode_operating_mode(&self, frame: Frame) -> TbsPg {
-        let mode = OperatingMode::from_u8(frame.0[6]);
-        let lock_flag = u16::from_le_bytes([frame.0[8], frame.0[9]]) >> 14;
+    fn decode_register_dump(&self, frame: &[u8]) -> TbsPg {
+        let mut registers = [0; RegisterDump::LEN];
+        registers.copy_from_slice(&frame[6..6 + RegisterDump::LEN]);
+        TbsPg::RegisterDump(RegisterDump { registers })
+    }
+
+    fn decode_operating_mode(&self, frame: &[u8]) -> TbsPg {
+        let mode = OperatingMode::from_u8(frame[6]);
+        let lock_flag = u16::from_le_bytes([frame[8], frame[9]]) >> 14;
         let installer_lock = match lock_flag {
             0 => InstallerLock::InstallerLockOff,
             1 => InstallerLock::InstallerLockOn,
@@ -476,3 +690,373 @@ impl Decoder {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::CustomMessage;
+
+    fn frame(bytes: &[u8]) -> Frame {
+        Frame(bytes.into())
+    }
+
+    #[test]
+    fn test_try_decode_bb1dc() {
+        let decoder = Decoder::new();
+        let decoded = decoder.try_decode(&frame(&[
+            0xAA, 0x00, 0xFF, 0x18, 0xF0, 0x08, 0x00, 0xB0, 0x6B, 0x04, 0xFA, 0x11, 0x7A, 0xFE,
+            0x4F, 0x99,
+        ]));
+        match decoded.map(|message| message.body) {
+            Ok(TbsPg::BasicQuantities {
+                bank: BankId::BANK_1,
+                data: quantities,
+            }) => {
+                assert_eq!(quantities.flags, StatusFlags(0xB000));
+                assert_eq!(quantities.voltage, Some(Volts::from_centivolts(1131)));
+                assert!(matches!(
+                    quantities.temperature,
+                    Temperature::NoSensorDetected
+                ));
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_try_decode_header() {
+        let decoder = Decoder::new();
+        let message = decoder
+            .try_decode(&frame(&[0xAA, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x03, 0x99]))
+            .unwrap();
+        assert_eq!(
+            message.header,
+            FrameHeader {
+                source: 0x00,
+                destination: 0xFF,
+                pgn: 0xFFFF,
+                length: 0,
+            }
+        );
+        assert!(message.header.is_broadcast());
+        assert!(matches!(message.body, TbsPg::Heartbeat));
+    }
+
+    #[test]
+    fn test_try_decode_bank_status_flags() {
+        let decoder = Decoder::new();
+        let decoded = decoder.try_decode(&Frame::build(
+            0x00,
+            0xFF,
+            0xF01A,
+            &[0xC0, 0xD0, 0x84, 0x1C, 0x10, 0x27, 0xFF, 0x7F],
+        ));
+        match decoded.map(|message| message.body) {
+            Ok(TbsPg::BankStatus {
+                bank: BankId::BANK_1,
+                data: status,
+            }) => {
+                assert_eq!(status.flags, StatusFlags(0xD0C0));
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_borrowed_from_parser() {
+        let mut parser = crate::frameparser::FrameParser::new();
+        parser.push(&[
+            0xAA, 0x00, 0xFF, 0x18, 0xF0, 0x08, 0x00, 0xB0, 0x6B, 0x04, 0xFA, 0x11, 0x7A, 0xFE,
+            0x4F, 0x99,
+        ]);
+        let decoder = Decoder::new();
+        let frame = parser.next_frame_ref().unwrap();
+        let message = decoder.decode(frame.0).unwrap();
+        assert_eq!(message.header.pgn, 0xF018);
+        assert_eq!(message.body.bank(), Some(BankId::BANK_1));
+    }
+
+    #[test]
+    fn test_registered_decoder() {
+        #[derive(Debug, PartialEq)]
+        struct Dump(usize);
+
+        let mut decoder = Decoder::new();
+        decoder.register(0xFFEA, None, |_: &FrameHeader, payload: &[u8]| {
+            Ok(TbsPg::Custom(CustomMessage::new(Dump(payload.len()))))
+        });
+        decoder.register(0xFFEA, Some(2), |header: &FrameHeader, _: &[u8]| {
+            Err(DecodeError::LengthMismatch {
+                pgn: header.pgn,
+                expected: 68,
+                actual: 2,
+            })
+        });
+
+        let message = decoder
+            .try_decode(&Frame::build(0x00, 0xFF, 0xFFEA, &[0x00; 0x44]))
+            .unwrap();
+        match message.body {
+            TbsPg::Custom(custom) => assert_eq!(custom.downcast::<Dump>().unwrap(), Dump(0x44)),
+            other => panic!("Unexpected result {:?}", other),
+        }
+        assert!(decoder
+            .try_decode(&Frame::build(0x00, 0xFF, 0xFFEA, &[0x00; 2]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_registered_decoder_overrides_built_in() {
+        let mut decoder = Decoder::new();
+        decoder.register(0xFFFF, None, |_: &FrameHeader, _: &[u8]| {
+            Ok(TbsPg::Custom(CustomMessage::new("heartbeat")))
+        });
+        let message = decoder
+            .try_decode(&frame(&[0xAA, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x03, 0x99]))
+            .unwrap();
+        match message.body {
+            TbsPg::Custom(custom) => assert!(custom.is::<&str>()),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_try_decode_register_dump() {
+        // Register dump as received in dump/dumped_btatt_values.log.
+        let mut bytes = vec![0xAA, 0x00, 0xFF, 0xEA, 0xFF, 0x44];
+        bytes.extend_from_slice(&[
+            0x00, 0x00, 0x40, 0x00, 0x00, 0x64, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00,
+            0x19, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x06, 0x09, 0x00, 0x00, 0x09, 0xB4,
+        ]);
+        bytes.resize(6 + 0x44, 0xFF);
+        bytes.extend_from_slice(&[0x43, 0x99]);
+
+        let decoder = Decoder::new();
+        match decoder
+            .try_decode(&frame(&bytes))
+            .map(|message| message.body)
+        {
+            Ok(TbsPg::RegisterDump(dump)) => {
+                assert_eq!(dump.used_registers().len(), 28);
+                assert_eq!(dump.u8_at(5), Some(0x64));
+                assert_eq!(dump.u8_at(6), Some(0xFF));
+                assert_eq!(dump.u16_at(14), Some(0x3619));
+                assert_eq!(dump.u16_at(26), Some(0xB409));
+                assert_eq!(dump.u32_at(8), Some(0xFFFF_FFFF));
+                assert_eq!(dump.u32_at(64), Some(0xFFFF_FFFF));
+                assert_eq!(dump.u32_at(65), None);
+                assert_eq!(dump.u8_at(RegisterDump::LEN), None);
+                assert_eq!(dump.u16_at(usize::MAX), None);
+                assert_eq!(dump.u32_at(usize::MAX - 2), None);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+        assert_eq!(
+            decoder
+                .try_decode(&Frame::build(0x00, 0xFF, 0xFFEA, &[0xFF; 8]))
+                .unwrap_err(),
+            DecodeError::LengthMismatch {
+                pgn: 0xFFEA,
+                expected: 0x44,
+                actual: 8,
+            }
+        );
+    }
+
+    #[test]
+    fn test_try_decode_address_claimed() {
+        let decoder = Decoder::new();
+        let payload = [0x0A, 0x5B, 0x75, 0xF2, 0xFF, 0x32, 0x24, 0x0A];
+        match decoder
+            .try_decode(&Frame::build(0x00, 0xFF, 0xEE00, &payload))
+            .map(|message| message.body)
+        {
+            Ok(TbsPg::AddressClaimed(claimed)) => {
+                assert_eq!(claimed.device_id, DeviceId::ExpertModular);
+                assert_eq!(claimed.brand_id, BrandId::TbsElectronics);
+                assert_eq!(claimed.serial_number, 227190006);
+                assert_eq!(claimed.name, AddressClaimName(0x0A24_32FF_F275_5B0A));
+                assert_eq!(claimed.name.identity_number(), 0x15_5B0A);
+                assert_eq!(claimed.name.manufacturer_code(), 0x793);
+                assert_eq!(claimed.name.device_instance(), 0xFF);
+                assert_eq!(claimed.name.function(), 0x32);
+                assert_eq!(claimed.name.device_class(), 0x12);
+                assert_eq!(claimed.name.system_instance(), 0x0A);
+                assert_eq!(claimed.name.industry_group(), 0);
+                assert!(!claimed.name.is_arbitrary_address_capable());
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let payload = [0x01, 0x00, 0x00, 0x00, 0x00, 0x89, 0x34, 0x12];
+        match decoder
+            .try_decode(&Frame::build(0x00, 0xFF, 0xEE00, &payload))
+            .map(|message| message.body)
+        {
+            Ok(TbsPg::AddressClaimed(claimed)) => {
+                assert_eq!(claimed.device_id, DeviceId::Other(0x1234));
+                assert_eq!(claimed.device_id.id(), 0x1234);
+                assert_eq!(claimed.brand_id, BrandId::Other(0x89));
+                assert_eq!(claimed.brand_id.id(), 0x89);
+                assert_eq!(claimed.name.industry_group(), 1);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_try_decode_power_and_charge() {
+        // Bank 1 power and charge as received in dump/dumped_btatt_values.log.
+        let payload = [0xFF, 0xBF, 0xFA, 0x11, 0x7A, 0x24, 0x07, 0x7A];
+        let decoder = Decoder::new();
+        match decoder
+            .try_decode(&Frame::build(0x00, 0xFF, 0xF019, &payload))
+            .map(|message| message.body)
+        {
+            Ok(TbsPg::PowerAndCharge { data, .. }) => {
+                assert_eq!(data.power, Some(Watts::from_deciwatts(-6)));
+                assert_eq!(
+                    data.consumed_amp_hours,
+                    Some(AmpHours::from_centiamp_hours(-2780))
+                );
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_try_decode_acknowledgement() {
+        // Acknowledgement of the send all command (PGN 0xF003), as received in
+        // dump/dumped_btatt_values.log.
+        let payload = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x03, 0xF0, 0x01];
+        match Decoder::new()
+            .try_decode(&Frame::build(0x00, 0xFF, 0xE800, &payload))
+            .map(|message| message.body)
+        {
+            Ok(TbsPg::Acknowledgement(ack)) => {
+                assert_eq!(ack.ack_type, AcknowledgementType::PositiveAcknowledgement);
+                assert_eq!(ack.pgn, 0xF003);
+                assert_eq!(ack.data_page, 1);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_try_decode_too_short() {
+        let decoder = Decoder::new();
+        assert_eq!(
+            decoder.try_decode(&frame(&[0xAA, 0x00, 0x99])).unwrap_err(),
+            DecodeError::TooShort { len: 3 }
+        );
+    }
+
+    #[test]
+    fn test_try_decode_checksum_mismatch() {
+        let decoder = Decoder::new();
+        assert_eq!(
+            decoder
+                .try_decode(&frame(&[0xAA, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x04, 0x99]))
+                .unwrap_err(),
+            DecodeError::ChecksumMismatch {
+                expected: 0x03,
+                actual: 0x04,
+                pgn: 0xFFFF
+            }
+        );
+    }
+
+    #[test]
+    fn test_try_decode_unknown_pgn() {
+        let decoder = Decoder::new();
+        let decoded = decoder.try_decode(&frame(&[0xAA, 0x00, 0xFF, 0x01, 0xF0, 0x00, 0x10, 0x99]));
+        match decoded.map(|message| message.body) {
+            Ok(TbsPg::Raw(raw)) => {
+                assert_eq!(raw.pgn, 0xF001);
+                assert!(raw.payload.is_empty());
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_try_decode_raw_payload_kept() {
+        let decoder = Decoder::new();
+        let payload: Vec<u8> = (0..0x44).collect();
+        let decoded = decoder.try_decode(&Frame::build(0x00, 0xFF, 0xFFE0, &payload));
+        match decoded.map(|message| message.body) {
+            Ok(TbsPg::Raw(raw)) => {
+                assert_eq!(raw.pgn, 0xFFE0);
+                assert_eq!(raw.payload, payload);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_try_decode_length_mismatch() {
+        let decoder = Decoder::new();
+        assert_eq!(
+            decoder
+                .try_decode(&Frame::build(0x00, 0xFF, 0xF018, &[0x00]))
+                .unwrap_err(),
+            DecodeError::LengthMismatch {
+                pgn: 0xF018,
+                expected: 8,
+                actual: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_try_decode_declared_length_mismatch() {
+        let decoder = Decoder::new();
+        // Heartbeat declaring one payload byte, without any payload.
+        assert_eq!(
+            decoder
+                .try_decode(&frame(&[0xAA, 0x00, 0xFF, 0xFF, 0xFF, 0x01, 0x02, 0x99]))
+                .unwrap_err(),
+            DecodeError::DeclaredLengthMismatch {
+                pgn: 0xFFFF,
+                declared: 1,
+                actual: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_try_decode_longer_payload() {
+        let decoder = Decoder::new();
+        let mut payload = vec![0x00, 0xB0, 0x6B, 0x04, 0xFA, 0x11, 0x7A, 0xFE];
+        payload.extend_from_slice(&[0x01, 0x02]);
+        let decoded = decoder.try_decode(&Frame::build(0x00, 0xFF, 0xF018, &payload));
+        assert!(matches!(
+            decoded.map(|message| message.body),
+            Ok(TbsPg::BasicQuantities { .. })
+        ));
+    }
+
+    #[test]
+    fn test_try_decode_banks() {
+        let payload = [0xFD, 0xBF, 0x80, 0x0C, 0xC8, 0x00, 0xFF, 0x03];
+        let decoded = Decoder::new()
+            .try_decode(&Frame::build(0x00, 0xFF, 0xF034, &payload))
+            .unwrap();
+        assert_eq!(decoded.body.bank(), Some(BankId::BANK_3));
+        assert!(matches!(decoded.body, TbsPg::BasicSetup { .. }));
+
+        // The fourth bank is only decoded when configured, its PGNs are extrapolated.
+        let bank_4_pgn = BankId::new(4).unwrap().pgn(BankMessageKind::BasicSetup);
+        assert_eq!(bank_4_pgn, 0xF03E);
+        let frame = Frame::build(0x00, 0xFF, bank_4_pgn, &payload);
+        assert!(matches!(
+            Decoder::new().try_decode(&frame).unwrap().body,
+            TbsPg::Raw(_)
+        ));
+        #[cfg(feature = "experimental")]
+        {
+            let decoded = Decoder::with_bank_count(4).try_decode(&frame).unwrap();
+            assert_eq!(decoded.body.bank(), BankId::new(4));
+        }
+    }
+}
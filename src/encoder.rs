@@ -0,0 +1,153 @@
+/*
+ * Copyright (c) 2024 Dominik Röttsches
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use crate::types::{Frame, END_BYTE, ESCAPE_BYTE, START_BYTE};
+
+/// FrameEncoder turns frames into the bytes that are sent over the wire to a TBS device.
+///
+/// Bytes between the start byte (0xAA) and the end byte (0x99) that have the value of a start, end
+/// or escape byte (0xA9) are bytestuffed: they are replaced with the escape byte, followed by the
+/// original byte XORed with 0x20. This is the inverse of what the
+/// [`FrameParser`](crate::frameparser::FrameParser) does.
+///
+/// # Example
+///
+/// ```rust
+/// use laad::encoder::FrameEncoder;
+///
+/// let encoder = FrameEncoder::new();
+/// // Request for address claimed (PGN 0xEE00), sent with PGN 0xEA00.
+/// let bytes = encoder.encode(0xFD, 0x00, 0xEA00, &[0x00, 0xEE, 0x00]);
+/// assert_eq!(
+///     bytes,
+///     [0xAA, 0xFD, 0x00, 0x00, 0xEA, 0x03, 0x00, 0xEE, 0x00, 0x28, 0x99]
+/// );
+/// ```
+pub struct FrameEncoder {}
+
+impl Default for FrameEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameEncoder {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Builds a frame from source address, destination address, PGN and payload, see
+    /// [`Frame::build`], and returns its bytestuffed bytes.
+    pub fn encode(&self, source: u8, destination: u8, pgn: u16, payload: &[u8]) -> Vec<u8> {
+        self.encode_frame(&Frame::build(source, destination, pgn, payload))
+    }
+
+    /// Returns the bytestuffed bytes of a frame, which must start with the start byte and end
+    /// with the end byte.
+    pub fn encode_frame(&self, frame: &Frame) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(frame.0.len() + 4);
+        self.encode_frame_into(frame, &mut bytes);
+        bytes
+    }
+
+    /// Appends the bytestuffed bytes of a frame to `bytes`.
+    pub fn encode_frame_into(&self, frame: &Frame, bytes: &mut Vec<u8>) {
+        if frame.0.len() < 2 {
+            return;
+        }
+        let inner = &frame.0[1..frame.0.len() - 1];
+        bytes.push(START_BYTE);
+        for &byte in inner {
+            if byte == START_BYTE || byte == END_BYTE || byte == ESCAPE_BYTE {
+                bytes.push(ESCAPE_BYTE);
+                bytes.push(byte ^ 0x20);
+            } else {
+                bytes.push(byte);
+            }
+        }
+        bytes.push(END_BYTE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frameparser::FrameParser;
+
+    const REQUEST_FOR_ADDRESS_CLAIMED: [u8; 11] = [
+        0xAA, 0xFD, 0x00, 0x00, 0xEA, 0x03, 0x00, 0xEE, 0x00, 0x28, 0x99,
+    ];
+    const SEND_ALL_COMMAND: [u8; 16] = [
+        0xAA, 0xFD, 0x00, 0x03, 0xF0, 0x08, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x10,
+        0x99,
+    ];
+
+    #[test]
+    fn test_encode_request_for_address_claimed() {
+        let encoder = FrameEncoder::new();
+        let bytes = encoder.encode(0xFD, 0x00, 0xEA00, &[0x00, 0xEE, 0x00]);
+        assert_eq!(bytes, REQUEST_FOR_ADDRESS_CLAIMED);
+    }
+
+    #[test]
+    fn test_encode_send_all_command() {
+        let encoder = FrameEncoder::new();
+        let bytes = encoder.encode(0xFD, 0x00, 0xF003, &[0xFF; 8]);
+        assert_eq!(bytes, SEND_ALL_COMMAND);
+    }
+
+    #[test]
+    fn test_encode_heartbeat() {
+        let encoder = FrameEncoder::new();
+        let bytes = encoder.encode(0x00, 0xFF, 0xFFFF, &[]);
+        assert_eq!(bytes, [0xAA, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x03, 0x99]);
+    }
+
+    #[test]
+    fn test_encode_bytestuffing() {
+        let encoder = FrameEncoder::new();
+        let bytes = encoder.encode(0xAA, 0x99, 0xF0A9, &[0x01]);
+        assert_eq!(
+            bytes,
+            [0xAA, 0xA9, 0x8A, 0xA9, 0xB9, 0xA9, 0x89, 0xF0, 0x01, 0x01, 0x22, 0x99]
+        );
+    }
+
+    #[test]
+    fn test_round_trip_through_parser() {
+        let encoder = FrameEncoder::new();
+        let mut parser = FrameParser::new();
+        let payloads: [&[u8]; 4] = [
+            &[],
+            &[0xAA, 0x99, 0xA9, 0x00],
+            &[0xFF; 8],
+            &[0x89, 0x8A, 0xB9, 0x20, 0xA9, 0xA9],
+        ];
+        for payload in payloads {
+            let frame = Frame::build(0x00, 0xFF, 0xF018, payload);
+            let frames: Vec<_> = parser.feed(&encoder.encode_frame(&frame)).collect();
+            assert_eq!(frames.len(), 1);
+            assert_eq!(frames[0].0, frame.0);
+            assert_eq!(&frames[0].0[6..6 + payload.len()], payload);
+        }
+    }
+}
@@ -20,15 +20,28 @@
  * SOFTWARE.
  */
 
-use crate::types::{Bytes, Frame};
-use regex::bytes::Regex;
+use crate::types::{Bytes, Frame, FrameRef, END_BYTE, ESCAPE_BYTE, START_BYTE};
 use tokio::sync::mpsc;
 use tracing::*;
 
 const MAX_BUFFERED_BYTES: usize = 1024;
 
+/// State of the frame scanner between two bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScanState {
+    /// Waiting for a start byte, bytes received in this state are discarded.
+    Idle,
+    /// Inside a frame, bytes are collected until the end byte.
+    InFrame,
+    /// Inside a frame, after an escape byte (0xA9). The next byte is de-bytestuffed.
+    Escaped,
+}
+
 pub struct FrameParser {
     buffered_bytes: Vec<u8>,
+    scan_position: usize,
+    state: ScanState,
+    frame_bytes: Vec<u8>,
 }
 
 impl Default for FrameParser {
@@ -38,21 +51,32 @@ impl Default for FrameParser {
 }
 
 /// FrameParser parses frames from a TBS battery monitor or charger.
-/// It uses a regex to find frames in a stream of bytes, and sends the frames to a Tokio channel (`mpsc::Sender`).
-/// The bytes in the frame are de-bytestuffed before being sent to the channel.
-/// Receives raw bytes from a `tokio::mpsc::Receiver`, identifies frames identified
-/// by a start byte (0xAA) and an end byte (0x99), using regular expressions in byte-mode.
-/// The frames are sent to a `tokio::mpsc::Sender` after de-bytestuffing.
-///
-/// # Arguments
+/// It scans a stream of bytes for frames identified by a start byte (0xAA) and an end byte (0x99),
+/// using a state machine that looks at each received byte only once. Bytes are de-bytestuffed while
+/// scanning. When a start byte is seen in the middle of a frame, the partial frame is discarded and
+/// the scanner resynchronizes on the latest start byte.
 ///
-/// * `rx` - A `tokio::mpsc::Receiver<Bytes>` that provides the raw bytes to be parsed.
-/// * `tx` - A `tokio::mpsc::Sender<Frame>` that receives the parsed frames after de-bytestuffing.
+/// The parser can be used synchronously by pushing bytes with [`push`](FrameParser::push) and
+/// pulling complete frames with [`next_frame`](FrameParser::next_frame) or
+/// [`feed`](FrameParser::feed), which does not depend on any async runtime.
+/// [`parse_frames`](FrameParser::parse_frames) wraps this into a Tokio task that reads raw bytes from
+/// a `tokio::mpsc::Receiver` and sends frames to a `tokio::mpsc::Sender`.
 ///
 /// # Example
 ///
 /// ```rust
 /// use laad::frameparser::FrameParser;
+///
+/// let mut parser = FrameParser::new();
+/// let bytes = [0xAA, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x03, 0x99, 0xAA, 0x00];
+/// let frames: Vec<_> = parser.feed(&bytes).collect();
+/// assert_eq!(frames.len(), 1);
+/// ```
+///
+/// Or as a Tokio task:
+///
+/// ```rust
+/// use laad::frameparser::FrameParser;
 /// use tokio::sync::mpsc;
 /// #[tokio::main]
 /// async fn main() {
@@ -68,85 +92,157 @@ impl FrameParser {
     pub fn new() -> Self {
         Self {
             buffered_bytes: Vec::new(),
+            scan_position: 0,
+            state: ScanState::Idle,
+            frame_bytes: Vec::new(),
+        }
+    }
+
+    /// Appends raw bytes received from a source to the parser's internal buffer.
+    /// Complete frames can then be retrieved with [`next_frame`](FrameParser::next_frame).
+    pub fn push(&mut self, bytes: &[u8]) {
+        if self.scan_position > 0 {
+            self.buffered_bytes.drain(..self.scan_position);
+            self.scan_position = 0;
+        }
+        self.buffered_bytes.extend_from_slice(bytes);
+    }
+
+    /// Returns the next complete frame from the buffered bytes, de-bytestuffed, or `None` if no
+    /// complete frame is buffered yet.
+    ///
+    /// Bytes outside of frames are discarded. A partial frame that grows beyond
+    /// `MAX_BUFFERED_BYTES` without an end byte is discarded as well.
+    pub fn next_frame(&mut self) -> Option<Frame> {
+        self.next_frame_ref().map(|frame| frame.to_frame())
+    }
+
+    /// Like [`next_frame`](FrameParser::next_frame), but borrows the de-bytestuffed frame from the
+    /// parser's internal buffer instead of allocating it. The frame can be decoded with
+    /// [`Decoder::decode`](crate::decoder::Decoder::decode) before pulling the next frame.
+    pub fn next_frame_ref(&mut self) -> Option<FrameRef<'_>> {
+        while self.scan_position < self.buffered_bytes.len() {
+            let byte = self.buffered_bytes[self.scan_position];
+            self.scan_position += 1;
+            if self.scan_byte(byte) {
+                return Some(FrameRef(&self.frame_bytes));
+            }
         }
+        self.buffered_bytes.clear();
+        self.scan_position = 0;
+        None
     }
 
-    fn de_bytestuff(&self, bytes: &[u8]) -> Vec<u8> {
-        if !bytes.contains(&0xA9) {
-            return bytes.to_vec();
+    /// Advances the state machine by one byte, returns true when a frame was completed.
+    /// The completed frame stays in `frame_bytes` until the next start byte.
+    fn scan_byte(&mut self, byte: u8) -> bool {
+        if byte == START_BYTE {
+            if self.state != ScanState::Idle {
+                debug!(
+                    "Resynchronizing on start byte, discarding {} bytes.",
+                    self.frame_bytes.len()
+                );
+            }
+            self.frame_bytes.clear();
+            self.frame_bytes.push(byte);
+            self.state = ScanState::InFrame;
+            return false;
         }
-        let mut de_bytestuffed = Vec::new();
-        let mut iter = bytes.iter();
-        while let Some(byte) = iter.next() {
-            if *byte == 0xA9 {
-                if let Some(next_byte) = iter.next() {
-                    de_bytestuffed.push(next_byte ^ 0x20);
+        match self.state {
+            ScanState::Idle => false,
+            ScanState::InFrame | ScanState::Escaped if byte == END_BYTE => {
+                // A dangling escape byte before the end byte is dropped.
+                self.frame_bytes.push(byte);
+                self.state = ScanState::Idle;
+                true
+            }
+            ScanState::InFrame if byte == ESCAPE_BYTE => {
+                self.state = ScanState::Escaped;
+                false
+            }
+            ScanState::InFrame | ScanState::Escaped => {
+                let byte = if self.state == ScanState::Escaped {
+                    byte ^ 0x20
+                } else {
+                    byte
+                };
+                self.state = ScanState::InFrame;
+                self.frame_bytes.push(byte);
+                if self.frame_bytes.len() > MAX_BUFFERED_BYTES {
+                    debug!(
+                        "Discarding {} bytes without end byte.",
+                        self.frame_bytes.len()
+                    );
+                    self.frame_bytes.clear();
+                    self.state = ScanState::Idle;
                 }
-            } else {
-                de_bytestuffed.push(*byte);
+                false
             }
         }
-        de_bytestuffed
+    }
+
+    /// Pushes `bytes` into the parser and returns an iterator over the complete frames that are
+    /// available afterwards.
+    pub fn feed(&mut self, bytes: &[u8]) -> Frames<'_> {
+        self.push(bytes);
+        Frames { parser: self }
     }
 
     /// Receives raw bytes from a tokio mpsc::Receiver, identifies frames identified
-    /// by a start byte (0xAA) and an end byte (0x99),
-    /// using regular expressions in byte-mode.
+    /// by a start byte (0xAA) and an end byte (0x99).
     /// The frames are sent to a tokio mpsc::Sender after de-bytestuffing.
     /// The function will continue to receive bytes until the tokio mpsc::Receiver is closed.
     /// The receiver of the de-bytestuffed frames can then decode the frames using the [`decode_frame`](crate::decoder::Decoder::decode_frame) function.
     pub async fn parse_frames(&mut self, mut rx: mpsc::Receiver<Bytes>, tx: mpsc::Sender<Frame>) {
         while let Some(bytes) = rx.recv().await {
-            const START_BYTE: u8 = 0xaa;
-            const END_BYTE: u8 = 0x99;
-
-            self.buffered_bytes.extend_from_slice(&bytes.0);
-
-            // This is less efficient than it could be, because it restarts the search on previous
-            // packets, but it's simpler to understand to use regexes here.
-            let re = Regex::new(&format!(
-                r"(?s-u)\x{:02X}(.*?)\x{:02X}",
-                START_BYTE, END_BYTE
-            ))
-            .unwrap();
-            let mut last_match_end = 0;
-            for cap in re.captures_iter(self.buffered_bytes.as_slice()) {
-                if let Some(matched) = cap.get(0) {
-                    last_match_end = matched.end();
-                    let matched_debytestuffed = self.de_bytestuff(matched.as_bytes());
-                    if let Err(e) = tx
-                        .send(Frame(matched_debytestuffed.into_boxed_slice()))
-                        .await
-                    {
-                        error!("Failed to send frame: {:?}", e);
-                    }
-                }
-            }
-            self.buffered_bytes.drain(..last_match_end);
-            debug!(
-                "Buffered bytes ({} bytes): {:?}",
-                self.buffered_bytes.len(),
-                self.buffered_bytes
-                    .iter()
-                    .map(|b| format!("0x{:02X}", b))
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            );
-
-            if self.buffered_bytes.len() > MAX_BUFFERED_BYTES {
-                if let Some(pos) = self.buffered_bytes.iter().position(|&x| x == START_BYTE) {
-                    self.buffered_bytes.drain(..pos);
-                } else {
-                    self.buffered_bytes.clear();
+            self.push(&bytes.0);
+            while let Some(frame) = self.next_frame() {
+                if let Err(e) = tx.send(frame).await {
+                    error!("Failed to send frame: {:?}", e);
                 }
             }
         }
     }
 }
+
+/// Iterator over the complete frames buffered in a [`FrameParser`], returned by
+/// [`FrameParser::feed`].
+pub struct Frames<'a> {
+    parser: &'a mut FrameParser,
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.parser.next_frame()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    impl FrameParser {
+        /// De-bytestuffs the bytes of a frame with the scanner, as `de_bytestuff` did before the
+        /// scanner de-bytestuffed while scanning.
+        fn de_bytestuff(&self, bytes: &[u8]) -> Vec<u8> {
+            let mut scanner = FrameParser::new();
+            scanner.state = ScanState::InFrame;
+            for &byte in bytes {
+                scanner.scan_byte(byte);
+            }
+            scanner.frame_bytes
+        }
+    }
+
+    fn parse_single(input: &[u8]) -> Vec<u8> {
+        let mut parser = FrameParser::new();
+        let frames: Vec<_> = parser.feed(input).collect();
+        assert_eq!(frames.len(), 1);
+        frames[0].0.to_vec()
+    }
+
     #[test]
     fn test_de_bytestuff_no_stuffing() {
         let parser = FrameParser::new();
@@ -208,4 +304,105 @@ mod tests {
         let output = parser.de_bytestuff(&input);
         assert_eq!(output, expected_output);
     }
+
+    #[test]
+    fn test_dangling_escape_before_end_byte() {
+        // The end byte is never bytestuffed, so it ends the frame even after an escape byte.
+        let input = vec![0xAA, 0x01, 0xA9, 0x99];
+        let expected_output = vec![0xAA, 0x01, 0x99];
+        let output = parse_single(&input);
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn test_feed_split_frames() {
+        let mut parser = FrameParser::new();
+        assert!(parser.feed(&[0x00, 0xAA, 0x00, 0xFF]).next().is_none());
+        let frames: Vec<_> = parser
+            .feed(&[0xFF, 0xFF, 0x00, 0x03, 0x99, 0xAA, 0xA9, 0x20, 0x99])
+            .collect();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(
+            &*frames[0].0,
+            &[0xAA, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x03, 0x99]
+        );
+        assert_eq!(&*frames[1].0, &[0xAA, 0x00, 0x99]);
+        assert!(parser.next_frame().is_none());
+    }
+
+    #[test]
+    fn test_split_escape_sequence() {
+        let mut parser = FrameParser::new();
+        assert!(parser.feed(&[0xAA, 0x01, 0xA9]).next().is_none());
+        let frames: Vec<_> = parser.feed(&[0x8A, 0x99]).collect();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(&*frames[0].0, &[0xAA, 0x01, 0xAA, 0x99]);
+    }
+
+    #[test]
+    fn test_resync_on_stray_start_byte() {
+        let input = [
+            0xAA, 0x00, 0xFF, 0x18, 0xAA, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x03, 0x99,
+        ];
+        let output = parse_single(&input);
+        assert_eq!(output, &input[4..]);
+    }
+
+    #[test]
+    fn test_garbage_between_frames() {
+        let mut parser = FrameParser::new();
+        let frames: Vec<_> = parser
+            .feed(&[
+                0x99, 0x01, 0xAA, 0x02, 0x99, 0x03, 0x99, 0xA9, 0xAA, 0x04, 0x99,
+            ])
+            .collect();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(&*frames[0].0, &[0xAA, 0x02, 0x99]);
+        assert_eq!(&*frames[1].0, &[0xAA, 0x04, 0x99]);
+    }
+
+    #[test]
+    fn test_overflow_discards_garbage() {
+        let mut parser = FrameParser::new();
+        parser.push(&[0x00; MAX_BUFFERED_BYTES + 1]);
+        assert!(parser.next_frame().is_none());
+        assert!(parser.buffered_bytes.is_empty());
+    }
+
+    #[test]
+    fn test_overflow_discards_unterminated_frame() {
+        let mut parser = FrameParser::new();
+        parser.push(&[0xAA]);
+        parser.push(&[0x00; MAX_BUFFERED_BYTES + 1]);
+        assert!(parser.next_frame().is_none());
+        assert!(parser.feed(&[0x01, 0x99]).next().is_none());
+    }
+
+    #[test]
+    fn test_next_frame_ref() {
+        let mut parser = FrameParser::new();
+        parser.push(&[0xAA, 0x00, 0xA9, 0x20, 0x99, 0x01, 0xAA, 0x02, 0x99]);
+        assert_eq!(
+            parser.next_frame_ref().unwrap().0,
+            &[0xAA, 0x00, 0x00, 0x99]
+        );
+        assert_eq!(parser.next_frame_ref().unwrap().0, &[0xAA, 0x02, 0x99]);
+        assert!(parser.next_frame_ref().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_parse_frames_channel() {
+        let (bytes_tx, bytes_rx) = mpsc::channel(5);
+        let (frames_tx, mut frames_rx) = mpsc::channel(5);
+        let mut parser = FrameParser::new();
+        tokio::spawn(async move {
+            parser.parse_frames(bytes_rx, frames_tx).await;
+        });
+        bytes_tx
+            .send(Bytes(vec![0xAA, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x03, 0x99]))
+            .await
+            .unwrap();
+        let frame = frames_rx.recv().await.unwrap();
+        assert_eq!(frame.0.len(), 8);
+    }
 }
@@ -25,6 +25,17 @@
 //!
 //! This library decodes frames from the TBS battery monitor and TBS charger products.
 //!
+//! Frames share the same framing on both, so the [frame parser](frameparser::FrameParser) handles
+//! links with monitors and chargers mixed. Messages are decoded for the battery monitor only.
+//! Decoding charger messages, such as output per channel, AC input, temperature, error codes and
+//! the charge algorithm, is blocked on the charger device IDs and PGN layouts, which are neither
+//! documented nor present in the available dumps. Until then, charger specific PGNs are returned
+//! as [`TbsPg::Raw`](protocol::TbsPg::Raw) and can be decoded by registering decoders with
+//! [`Decoder::register`](decoder::Decoder::register).
+//!
+//! Telling stale, synchronized or erroneous measurements apart is blocked on the meaning of the
+//! [status flags](protocol::StatusFlags), which is not documented. The flags are kept as received.
+//!
 //! Example usage (see also **examples/laadreader/main.rs**):
 //!
 //! ```rust
@@ -63,11 +74,34 @@
 //! }
 //! ```
 
+/// Client sends commands to devices and waits for their acknowledgements or responses.
+pub mod client;
+/// TbsCodec frames and encodes TBS frames for `tokio_util::codec::Framed`.
+#[cfg(feature = "codec")]
+pub mod codec;
+/// Commands build the frames sent to devices, such as requests for PGNs.
+pub mod command;
 /// Decoder decodes frames into protocol types.
 pub mod decoder;
-/// FrameParser identifies frames in a stream of bytes and sends the frames to a Tokio channel.
+/// FrameEncoder bytestuffs frames for sending them to a device.
+pub mod encoder;
+/// FrameParser identifies frames in a stream of bytes, synchronously or as a Tokio task sending frames to a channel.
 pub mod frameparser;
 /// Protocol defines the TBS protocol and decoded information for frame types that are understood.
 pub mod protocol;
+/// SerialTransport reads and writes TBS frames over a serial port.
+#[cfg(feature = "serial")]
+pub mod serial;
+/// TcpTransport and TcpServer read and re-broadcast TBS frames over TCP.
+pub mod tcp;
+/// Transport defines links to devices, receiving bytes and sending encoded frames.
+pub mod transport;
 /// Basic types for bytes and frames.
 pub mod types;
+/// Units define the physical units of decoded quantities, such as volts and amperes.
+pub mod units;
+
+/// Runs the code examples of the README as doctests, so they keep compiling.
+#[cfg(doctest)]
+#[doc = include_str!("../README.md")]
+struct ReadmeDoctests;
@@ -23,10 +23,111 @@
 //! This module defines known enums, structs, and implementations related to the protocol
 //! for the TBS battery monitors and chargers.
 
+use crate::types::FrameHeader;
+use crate::units::{AmpHours, Amperes, Celsius, Percent, Volts, Watts};
+use std::any::Any;
+
+/// Status flags sent along with measurements in the [basic quantities](BasicQuantities),
+/// [power and charge](PowerAndCharge), [bank status](BankStatus) and [charge state](ChargeState)
+/// messages, as received.
+///
+/// The meaning of the individual bits is not documented, so whether a measurement is stale,
+/// synchronized or in error cannot be told from them yet.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatusFlags(pub u16);
+
+/// PGNs of messages other than the per-bank messages, whose PGNs are returned by
+/// [`BankId::pgn`].
+pub mod pgn {
+    pub const ACKNOWLEDGEMENT: u16 = 0xE800;
+    pub const REQUEST: u16 = 0xEA00;
+    pub const ADDRESS_CLAIMED: u16 = 0xEE00;
+    pub const DEVICE_NAME: u16 = 0xF000;
+    pub const VERSION_INFO: u16 = 0xF002;
+    pub const OPERATING_MODE: u16 = 0xF00E;
+    pub const REGISTER_DUMP: u16 = 0xFFEA;
+    pub const HEARTBEAT: u16 = 0xFFFF;
+}
+
+/// Identifies a battery bank of a battery monitor, numbered from 1.
+///
+/// Each bank has its own set of PGNs for the per-bank messages, see [`BankMessageKind`]. The
+/// Expert Modular supports three banks. For further banks, [`pgn`](BankId::pgn) and
+/// [`from_pgn`](BankId::from_pgn) continue the layout of the first three banks. This is an
+/// extrapolation that has not been verified against a device, the PGNs may be used for other
+/// messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BankId(u8);
+
+/// The kinds of messages sent per battery bank. The value is the offset of the PGN from the
+/// first PGN of a bank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BankMessageKind {
+    BasicQuantities = 0,
+    PowerAndCharge = 1,
+    BankStatus = 2,
+    ChargeState = 6,
+    BasicSetup = 8,
+}
+
+impl BankId {
+    pub const BANK_1: BankId = BankId(1);
+    pub const BANK_2: BankId = BankId(2);
+    pub const BANK_3: BankId = BankId(3);
+
+    const FIRST_BANK_PGN: u16 = 0xF018;
+    const PGNS_PER_BANK: u16 = 10;
+
+    /// Returns the bank with the given number, or `None` for 0.
+    pub fn new(number: u8) -> Option<BankId> {
+        (number > 0).then_some(BankId(number))
+    }
+
+    /// The number of the bank, starting at 1.
+    pub fn number(&self) -> u8 {
+        self.0
+    }
+
+    /// The zero-based index of the bank, for indexing arrays of banks.
+    pub fn index(&self) -> usize {
+        self.0 as usize - 1
+    }
+
+    /// Returns the PGN of a message of the given kind for this bank.
+    pub fn pgn(&self, kind: BankMessageKind) -> u16 {
+        Self::FIRST_BANK_PGN + self.index() as u16 * Self::PGNS_PER_BANK + kind as u16
+    }
+
+    /// Returns the bank and the kind of message for a PGN of a per-bank message. PGNs after the
+    /// third bank are extrapolated, see [`BankId`].
+    pub fn from_pgn(pgn: u16) -> Option<(BankId, BankMessageKind)> {
+        let offset = pgn.checked_sub(Self::FIRST_BANK_PGN)?;
+        let number = u8::try_from(offset / Self::PGNS_PER_BANK + 1).ok()?;
+        let kind = match offset % Self::PGNS_PER_BANK {
+            0 => BankMessageKind::BasicQuantities,
+            1 => BankMessageKind::PowerAndCharge,
+            2 => BankMessageKind::BankStatus,
+            6 => BankMessageKind::ChargeState,
+            8 => BankMessageKind::BasicSetup,
+            _ => return None,
+        };
+        Some((BankId(number), kind))
+    }
+}
+
+impl std::fmt::Display for BankId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Bank {}", self.0)
+    }
+}
+
 /// Represents the different stages of charging, used in the [charge state](ChargeState) message.
 #[derive(Debug, Default)]
 #[allow(dead_code)]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChargeStage {
     Waiting = 0,
     SoftStart = 1,
@@ -47,6 +148,7 @@ pub enum ChargeStage {
 /// Represents the state of an indicator.
 #[derive(Debug, Default)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IndicatorState {
     On,
     #[default]
@@ -58,7 +160,9 @@ pub enum IndicatorState {
 /// Represents the state of charge including indicators for ranges of charge levels.
 #[derive(Debug, Default)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChargeState {
+    pub flags: StatusFlags,
     pub stage: ChargeStage,
     pub indicator_0_49: IndicatorState,
     pub indicator_50_79: IndicatorState,
@@ -69,6 +173,7 @@ pub struct ChargeState {
 /// Represents the remaining time for charging or other operations, used in the [bank status](BankStatus) message.
 #[derive(Debug, Default)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RemainingTime {
     Minutes(u16),
     Charging,
@@ -79,8 +184,9 @@ pub enum RemainingTime {
 /// Represents the state of health of the battery, , used in the [bank status](BankStatus) message.
 #[derive(Debug, Default)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StateOfHealth {
-    HealthPercentage(f32),
+    HealthPercentage(Percent),
     #[default]
     Unavailable,
     Initializing,
@@ -89,8 +195,9 @@ pub enum StateOfHealth {
 /// Represents the state of charge of the battery, used in the [bank status](BankStatus) message.
 #[derive(Debug, Default)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StateOfCharge {
-    ChargePercentage(f32),
+    ChargePercentage(Percent),
     #[default]
     Unavailable,
     Initializing,
@@ -99,7 +206,9 @@ pub enum StateOfCharge {
 /// Represents the status of a battery bank.
 #[derive(Debug, Default)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BankStatus {
+    pub flags: StatusFlags,
     pub state_of_charge: StateOfCharge,
     pub state_of_health: StateOfHealth,
     // in minutes, not available when charging.
@@ -109,6 +218,7 @@ pub struct BankStatus {
 /// Represents the version information of firmware, hardware, bootloader, and auxiliary components, used in the [version info](VersionInfo) message.
 #[derive(Debug, Default)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Version {
     pub major: u32,
     pub minor: u8,
@@ -118,6 +228,7 @@ pub struct Version {
 /// Contains version information for firmware, hardware, bootloader, and auxiliary components.
 #[derive(Debug, Default)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VersionInfo {
     pub firmware_version: Version,
     pub hardware_version: Version,
@@ -128,8 +239,9 @@ pub struct VersionInfo {
 /// Represents the temperature in degrees Celsius or other states, used in the [basic quantities](BasicQuantities) message.
 #[derive(Debug)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Temperature {
-    DegreesCelsius(f32),
+    DegreesCelsius(Celsius),
     Unavailable,
     NoSensorDetected,
 }
@@ -137,53 +249,149 @@ pub enum Temperature {
 /// Represents so called "basic quantities" such as voltage, current, and temperature.
 #[derive(Debug)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BasicQuantities {
-    // TODO: Flag state.
-    pub voltage: Option<f32>,
-    pub current: Option<f32>,
+    pub flags: StatusFlags,
+    pub voltage: Option<Volts>,
+    pub current: Option<Amperes>,
     pub temperature: Temperature,
 }
 
 /// Represents power and charge information.
 #[derive(Debug)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PowerAndCharge {
-    // in W.
-    pub power: Option<f32>,
-    // in Ah.
-    pub consumed_amp_hours: Option<f32>,
+    pub flags: StatusFlags,
+    pub power: Option<Watts>,
+    pub consumed_amp_hours: Option<AmpHours>,
 }
 
 /// Represents the device ID, used in the [address claimed](AddressClaimed) message.
-#[derive(Debug, Default)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DeviceId {
-    ExpertModular = 0x0A24,
-    #[default]
-    Unknown,
+    ExpertModular,
+    /// A device ID not known to laad, such as of third-party or newer devices.
+    Other(u16),
+}
+
+impl DeviceId {
+    /// Returns the device ID as sent in the address claimed message.
+    pub fn id(&self) -> u16 {
+        match self {
+            DeviceId::ExpertModular => 0x0A24,
+            DeviceId::Other(id) => *id,
+        }
+    }
 }
 
 /// Represents the brand ID, used in the [address claimed](AddressClaimed) message.
-#[derive(Debug, Default)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BrandId {
-    TbsElectronics = 0x32,
-    #[default]
-    Unknown,
+    TbsElectronics,
+    /// A brand ID not known to laad.
+    Other(u8),
+}
+
+impl BrandId {
+    /// Returns the brand ID as sent in the address claimed message.
+    pub fn id(&self) -> u8 {
+        match self {
+            BrandId::TbsElectronics => 0x32,
+            BrandId::Other(id) => *id,
+        }
+    }
+}
+
+/// The 64-bit NAME of a device, sent in the [address claimed](AddressClaimed) message, with its
+/// fields as laid out by J1939 and NMEA 2000.
+///
+/// TBS devices fill the NAME with their own serial number, brand ID and device ID, which laad
+/// decodes into [`AddressClaimed`], so the fields do not necessarily carry their J1939 meaning.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct AddressClaimName(pub u64);
+
+impl AddressClaimName {
+    /// Identity number (21 bits), unique per manufacturer.
+    pub fn identity_number(&self) -> u32 {
+        (self.0 & 0x1F_FFFF) as u32
+    }
+
+    /// Manufacturer code (11 bits).
+    pub fn manufacturer_code(&self) -> u16 {
+        ((self.0 >> 21) & 0x7FF) as u16
+    }
+
+    /// Device instance (8 bits), the lower 3 bits being the ECU instance and the upper 5 bits
+    /// the function instance.
+    pub fn device_instance(&self) -> u8 {
+        (self.0 >> 32) as u8
+    }
+
+    /// Device function (8 bits), specific to the device class.
+    pub fn function(&self) -> u8 {
+        (self.0 >> 40) as u8
+    }
+
+    /// Device class (7 bits), called vehicle system in J1939.
+    pub fn device_class(&self) -> u8 {
+        ((self.0 >> 49) & 0x7F) as u8
+    }
+
+    /// System instance (4 bits).
+    pub fn system_instance(&self) -> u8 {
+        ((self.0 >> 56) & 0x0F) as u8
+    }
+
+    /// Industry group (3 bits).
+    pub fn industry_group(&self) -> u8 {
+        ((self.0 >> 60) & 0x07) as u8
+    }
+
+    /// Whether the device can claim an arbitrary address.
+    pub fn is_arbitrary_address_capable(&self) -> bool {
+        self.0 >> 63 == 1
+    }
+}
+
+impl std::fmt::Debug for AddressClaimName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AddressClaimName")
+            .field("identity_number", &self.identity_number())
+            .field("manufacturer_code", &self.manufacturer_code())
+            .field("device_instance", &self.device_instance())
+            .field("function", &self.function())
+            .field("device_class", &self.device_class())
+            .field("system_instance", &self.system_instance())
+            .field("industry_group", &self.industry_group())
+            .field(
+                "arbitrary_address_capable",
+                &self.is_arbitrary_address_capable(),
+            )
+            .finish()
+    }
 }
 
 /// Represents the address claimed by a device.
 #[derive(Debug)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AddressClaimed {
     pub device_id: DeviceId,
     pub brand_id: BrandId,
     pub serial_number: u32,
+    /// The NAME the brand ID, device ID and serial number are decoded from.
+    pub name: AddressClaimName,
 }
 
 /// Represents the enablement state of a bank, used in the [basic setup](BasicSetup) message.
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BankEnable {
     Disabled = 0,
     Enabled = 1,
@@ -192,8 +400,9 @@ pub enum BankEnable {
 }
 
 /// Represents the chosen name of a battery bank, as configured by the user.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BankName {
     BatteryBank1 = 0,
     BatteryBank2 = 1,
@@ -224,16 +433,18 @@ pub enum BankName {
 }
 
 /// Represents the capacity of a bank in ampere-hours.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BankCapacity {
     CapacityAh(u16),
     ParameterNotAvailable,
 }
 
 /// Represents the type of battery.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BatteryType {
     Flooded = 2000,
     Gel = 3000,
@@ -243,8 +454,9 @@ pub enum BatteryType {
 }
 
 /// Represents the basic setup of a battery bank, whether it is enabled or not, its name (from [BankName]), and the battery type, from [BatteryType].
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BasicSetup {
     pub bank_enable: BankEnable,
     pub bank_name: BankName,
@@ -253,8 +465,9 @@ pub struct BasicSetup {
 }
 
 /// Represents the type of acknowledgement, used in the [acknowledgement](Acknowledgement) message.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AcknowledgementType {
     PositiveAcknowledgement = 0,
     NegativeAcknowledgement = 1,
@@ -265,9 +478,13 @@ pub enum AcknowledgementType {
 
 /// Represents an acknowledgement message, received when a request for a PGN was sent.
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Acknowledgement {
     pub ack_type: AcknowledgementType,
     pub pgn: u16,
+    /// The data page of the acknowledged PGN, see
+    /// [`Command::Request`](crate::command::Command::Request).
+    pub data_page: u8,
 }
 
 impl std::fmt::Debug for Acknowledgement {
@@ -275,6 +492,7 @@ impl std::fmt::Debug for Acknowledgement {
         f.debug_struct("Acknowledgement")
             .field("ack_type", &self.ack_type)
             .field("pgn", &format_args!("0x{:04X}", self.pgn))
+            .field("data_page", &self.data_page)
             .finish()
     }
 }
@@ -287,18 +505,74 @@ pub struct DeviceName {
 
 impl std::fmt::Debug for DeviceName {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let null_terminated_name = std::str::from_utf8(&self.name)
-            .unwrap_or("")
-            .trim_end_matches('\0');
+        let name = String::from_utf8_lossy(&self.name);
         f.debug_struct("DeviceName")
-            .field("name", &null_terminated_name)
+            .field("name", &name.trim_end_matches('\0'))
+            .finish()
+    }
+}
+
+/// Dump of internal registers and state of a device, broadcast with PGN 0xFFEA, for example
+/// by the Battery Monitor Expert Modular.
+///
+/// The layout of the dump is not documented, so the registers are kept as received. They can be
+/// read as little-endian values at byte offsets, including 0xFF bytes, as whether these mark
+/// unused registers is not known. The configuration and counters in the dump are not decoded into typed fields, as which offsets
+/// hold which values is not known.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RegisterDump {
+    pub registers: [u8; RegisterDump::LEN],
+}
+
+impl RegisterDump {
+    /// Length of the dump in bytes.
+    pub const LEN: usize = 68;
+
+    /// Returns the byte at `offset`, or `None` if it is out of range.
+    pub fn u8_at(&self, offset: usize) -> Option<u8> {
+        self.registers.get(offset).copied()
+    }
+
+    /// Returns the little-endian u16 at `offset`, or `None` if it is out of range.
+    pub fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes = self.registers.get(offset..offset.checked_add(2)?)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Returns the little-endian u32 at `offset`, or `None` if it is out of range.
+    pub fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes = self.registers.get(offset..offset.checked_add(4)?)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Returns the registers up to the trailing 0xFF bytes, as shown by the `Debug` output.
+    pub fn used_registers(&self) -> &[u8] {
+        let used_len = self
+            .registers
+            .iter()
+            .rposition(|&b| b != 0xFF)
+            .map_or(0, |last| last + 1);
+        &self.registers[..used_len]
+    }
+}
+
+impl std::fmt::Debug for RegisterDump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hex: String = self
+            .used_registers()
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+        f.debug_struct("RegisterDump")
+            .field("registers", &hex)
             .finish()
     }
 }
 
 /// Represents the operating mode of a device, used in the [operating mode status](OperatingModeStatus).
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OperatingMode {
     DeviceOff = 0,
     DeviceBooting = 1,
@@ -311,8 +585,9 @@ pub enum OperatingMode {
 }
 
 /// Represents the installer lock state, used in the [operating mode status](OperatingModeStatus).
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InstallerLock {
     InstallerLockOff = 0,
     InstallerLockOn = 1,
@@ -320,37 +595,332 @@ pub enum InstallerLock {
 }
 
 /// Operating mode status of a device.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OperatingModeStatus {
     pub mode: OperatingMode,
     pub installer_lock: InstallerLock,
 }
 
+/// A message with a PGN that the decoder does not know, with its payload kept undecoded.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RawMessage {
+    pub pgn: u16,
+    pub payload: Vec<u8>,
+}
+
+impl std::fmt::Debug for RawMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawMessage")
+            .field("pgn", &format_args!("0x{:04X}", self.pgn))
+            .field("payload", &format_args!("{:02X?}", self.payload))
+            .finish()
+    }
+}
+
+/// Values produced by decoders registered with
+/// [`Decoder::register`](crate::decoder::Decoder::register), carried in [`TbsPg::Custom`].
+pub trait CustomValue: Any + std::fmt::Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: Any + std::fmt::Debug + Send + Sync> CustomValue for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// A message decoded by an application-provided decoder, see
+/// [`Decoder::register`](crate::decoder::Decoder::register).
+#[derive(Debug)]
+pub struct CustomMessage(Box<dyn CustomValue>);
+
+impl CustomMessage {
+    pub fn new<T: CustomValue>(value: T) -> Self {
+        Self(Box::new(value))
+    }
+
+    /// Returns true if the message holds a value of type `T`.
+    pub fn is<T: Any>(&self) -> bool {
+        (*self.0).as_any().is::<T>()
+    }
+
+    /// Returns a reference to the value, if it is of type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        (*self.0).as_any().downcast_ref::<T>()
+    }
+
+    /// Returns the value if it is of type `T`, or the message otherwise.
+    pub fn downcast<T: Any>(self) -> Result<T, Self> {
+        if self.is::<T>() {
+            Ok(*self.0.into_any().downcast::<T>().unwrap())
+        } else {
+            Err(self)
+        }
+    }
+}
+
 /// TBS protocol messages.
+///
+/// Messages for battery banks carry the [bank](BankId) they refer to. Before, there were separate
+/// variants per bank, which map to the bank-indexed variants as follows:
+///
+/// | Previous variants             | Variant                                       |
+/// |-------------------------------|-----------------------------------------------|
+/// | `Bb1dc`, `Bb2dc`, `Bb3dc`     | [`TbsPg::BasicQuantities`] with bank 1, 2, 3  |
+/// | `Bb1pc`, `Bb2pc`, `Bb3pc`     | [`TbsPg::PowerAndCharge`] with bank 1, 2, 3   |
+/// | `Bb1st`, `Bb2st`, `Bb3st`     | [`TbsPg::BankStatus`] with bank 1, 2, 3       |
+/// | `Bb1cs`, `Bb2cs`, `Bb3cs`     | [`TbsPg::ChargeState`] with bank 1, 2, 3      |
+/// | `Bb1bs`, `Bb2bs`, `Bb3bs`     | [`TbsPg::BasicSetup`] with bank 1, 2, 3       |
+///
+/// For example, `TbsPg::Bb2st(status)` becomes
+/// `TbsPg::BankStatus { bank: BankId::BANK_2, data: status }`.
 #[derive(Debug)]
 #[allow(dead_code)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TbsPg {
-    Bb1dc(BasicQuantities),
-    Bb2dc(BasicQuantities),
-    Bb3dc(BasicQuantities),
-    Bb1pc(PowerAndCharge),
-    Bb2pc(PowerAndCharge),
-    Bb3pc(PowerAndCharge),
-    Bb1st(BankStatus),
-    Bb2st(BankStatus),
-    Bb3st(BankStatus),
-    Bb1cs(ChargeState),
-    Bb2cs(ChargeState),
-    Bb3cs(ChargeState),
-    Bb1bs(BasicSetup),
-    Bb2bs(BasicSetup),
-    Bb3bs(BasicSetup),
+    BasicQuantities {
+        bank: BankId,
+        data: BasicQuantities,
+    },
+    PowerAndCharge {
+        bank: BankId,
+        data: PowerAndCharge,
+    },
+    BankStatus {
+        bank: BankId,
+        data: BankStatus,
+    },
+    ChargeState {
+        bank: BankId,
+        data: ChargeState,
+    },
+    BasicSetup {
+        bank: BankId,
+        data: BasicSetup,
+    },
     AddressClaimed(AddressClaimed),
     VersionInfo(VersionInfo),
     Heartbeat,
     Acknowledgement(Acknowledgement),
     DeviceName(DeviceName),
     OperatingModeStatus(OperatingModeStatus),
+    RegisterDump(RegisterDump),
+    /// A request for the message with the given PGN, see
+    /// [`Command::Request`](crate::command::Command::Request).
+    Request {
+        pgn: u16,
+        data_page: u8,
+    },
+    /// A message with a valid checksum, but a PGN that is not decoded.
+    Raw(RawMessage),
+    /// A message decoded by a decoder registered by the application. Serialized as
+    /// `{"Custom": null}` and not deserialized, as the type of its value is only known to the
+    /// application.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "serde_impls::serialize_custom", skip_deserializing)
+    )]
+    Custom(CustomMessage),
+    /// A frame that could not be decoded, see [`DecodeError`](crate::decoder::DecodeError).
     Unknown,
 }
+
+impl TbsPg {
+    /// Returns the bank a message refers to, for per-bank messages.
+    pub fn bank(&self) -> Option<BankId> {
+        match self {
+            TbsPg::BasicQuantities { bank, .. }
+            | TbsPg::PowerAndCharge { bank, .. }
+            | TbsPg::BankStatus { bank, .. }
+            | TbsPg::ChargeState { bank, .. }
+            | TbsPg::BasicSetup { bank, .. } => Some(*bank),
+            _ => None,
+        }
+    }
+}
+
+/// A decoded message together with the header of the frame it was received in, to tell apart
+/// messages from different devices.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Message {
+    pub header: FrameHeader,
+    pub body: TbsPg,
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::{BankId, CustomMessage, DeviceName, RegisterDump};
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Serialized as the bank number, starting at 1.
+    impl Serialize for BankId {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_u8(self.number())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for BankId {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            BankId::new(u8::deserialize(deserializer)?)
+                .ok_or_else(|| D::Error::custom("bank numbers start at 1"))
+        }
+    }
+
+    /// Serialized as a string, without the null termination. Bytes that are not valid UTF-8 are
+    /// replaced with U+FFFD.
+    impl Serialize for DeviceName {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let len = self.name.iter().position(|&b| b == 0).unwrap_or(32);
+            serializer.serialize_str(&String::from_utf8_lossy(&self.name[..len]))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for DeviceName {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let string = String::deserialize(deserializer)?;
+            let mut name = [0; 32];
+            name.get_mut(..string.len())
+                .ok_or_else(|| D::Error::custom("device name longer than 32 bytes"))?
+                .copy_from_slice(string.as_bytes());
+            Ok(DeviceName { name })
+        }
+    }
+
+    pub(super) fn serialize_custom<S: Serializer>(
+        _: &CustomMessage,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_unit()
+    }
+
+    /// Serialized as a sequence of the bytes of all registers.
+    impl Serialize for RegisterDump {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            #[derive(Serialize)]
+            struct RegisterDump<'a> {
+                registers: &'a [u8],
+            }
+            RegisterDump {
+                registers: &self.registers,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for RegisterDump {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            #[derive(Deserialize)]
+            struct RegisterDump {
+                registers: Vec<u8>,
+            }
+            let dump = RegisterDump::deserialize(deserializer)?;
+            let registers = dump.registers.try_into().map_err(|registers: Vec<u8>| {
+                D::Error::invalid_length(registers.len(), &"68 registers")
+            })?;
+            Ok(super::RegisterDump { registers })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::{
+            decoder::Decoder,
+            frameparser::FrameParser,
+            protocol::{CustomMessage, Message, TbsPg},
+            types::Frame,
+        };
+        use serde_json::json;
+
+        fn decode_json(payload: &[u8], pgn: u16) -> serde_json::Value {
+            let message = Decoder::new()
+                .try_decode(&Frame::build(0x00, 0xFF, pgn, payload))
+                .unwrap();
+            serde_json::to_value(&message.body).unwrap()
+        }
+
+        #[test]
+        fn test_serialize_formats() {
+            let mut name = [0; 32];
+            name[..12].copy_from_slice(b"Akkumonitori");
+            assert_eq!(
+                decode_json(&name, 0xF000),
+                json!({ "DeviceName": "Akkumonitori" })
+            );
+            assert_eq!(
+                decode_json(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x03, 0xF0, 0x01], 0xE800),
+                json!({ "Acknowledgement": {
+                    "ack_type": "PositiveAcknowledgement",
+                    "pgn": 0xF003,
+                    "data_page": 1,
+                }})
+            );
+            assert_eq!(
+                decode_json(&[0x00, 0xB0, 0x6B, 0x04, 0xFB, 0x11, 0x7A, 0xFE], 0xF018),
+                json!({ "BasicQuantities": {
+                    "bank": 1,
+                    "data": {
+                        "flags": 0xB000,
+                        "voltage": 11.31,
+                        "current": -0.05,
+                        "temperature": "NoSensorDetected",
+                    },
+                }})
+            );
+        }
+
+        #[test]
+        fn test_serialize_non_utf8_device_name() {
+            // A name set in Latin-1, "Akku" followed by an e with acute accent.
+            let mut name = [0; 32];
+            name[..5].copy_from_slice(b"Akku\xE9");
+            assert_eq!(
+                decode_json(&name, 0xF000),
+                json!({ "DeviceName": "Akku\u{FFFD}" })
+            );
+        }
+
+        #[test]
+        fn test_serialize_custom() {
+            let custom = TbsPg::Custom(CustomMessage::new(42u8));
+            assert_eq!(
+                serde_json::to_value(&custom).unwrap(),
+                json!({ "Custom": null })
+            );
+        }
+
+        #[test]
+        fn test_round_trip_dump() {
+            let dump = include_str!("../dump/dumped_btatt_values.log");
+            let bytes: Vec<u8> = dump
+                .lines()
+                .flat_map(|line| {
+                    (0..line.len() / 2)
+                        .map(move |i| u8::from_str_radix(&line[2 * i..2 * i + 2], 16).unwrap())
+                })
+                .collect();
+            let decoder = Decoder::new();
+            let mut parser = FrameParser::new();
+            let mut decoded = 0;
+            for frame in parser.feed(&bytes) {
+                let Ok(message) = decoder.try_decode(&frame) else {
+                    continue;
+                };
+                let json = serde_json::to_string(&message).unwrap();
+                let round_trip: Message = serde_json::from_str(&json).unwrap();
+                assert_eq!(serde_json::to_string(&round_trip).unwrap(), json);
+                assert!(!matches!(round_trip.body, TbsPg::Unknown));
+                decoded += 1;
+            }
+            assert!(decoded > 100);
+        }
+    }
+}
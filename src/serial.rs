@@ -0,0 +1,202 @@
+/*
+ * Copyright (c) 2024 Dominik Röttsches
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use crate::transport::{Transport, TransportEvent};
+use crate::types::Bytes;
+use std::io;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_serial::SerialPortBuilderExt;
+pub use tokio_serial::{DataBits, Parity, SerialStream, StopBits};
+
+const READ_BUFFER_SIZE: usize = 256;
+
+/// Settings of a serial port, such as a TBS wired interface or a USB-serial adapter.
+///
+/// Only available with the `serial` feature.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use laad::serial::{Parity, SerialConfig, StopBits};
+/// let transport = SerialConfig::new("/dev/ttyUSB0", 19200)
+///     .with_parity(Parity::Even)
+///     .with_stop_bits(StopBits::Two)
+///     .open()
+///     .expect("Failed to open serial port");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialConfig {
+    path: String,
+    baud_rate: u32,
+    data_bits: DataBits,
+    parity: Parity,
+    stop_bits: StopBits,
+}
+
+impl SerialConfig {
+    /// Creates settings for the port at `path` with 8 data bits, no parity and one stop bit.
+    pub fn new(path: impl Into<String>, baud_rate: u32) -> Self {
+        Self {
+            path: path.into(),
+            baud_rate,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+        }
+    }
+
+    pub fn with_baud_rate(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    pub fn with_data_bits(mut self, data_bits: DataBits) -> Self {
+        self.data_bits = data_bits;
+        self
+    }
+
+    pub fn with_parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    pub fn with_stop_bits(mut self, stop_bits: StopBits) -> Self {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    /// Opens the port, see [`SerialTransport`].
+    pub fn open(&self) -> io::Result<SerialTransport> {
+        let stream = tokio_serial::new(&self.path, self.baud_rate)
+            .data_bits(self.data_bits)
+            .parity(self.parity)
+            .stop_bits(self.stop_bits)
+            .open_native_async()?;
+        Ok(SerialTransport::new(stream))
+    }
+}
+
+/// SerialTransport is a [`Transport`] over a serial port. It reports
+/// [`TransportEvent::Connected`] once and [`TransportEvent::Closed`] when the port is closed.
+///
+/// Only available with the `serial` feature.
+pub struct SerialTransport {
+    stream: SerialStream,
+    buffer: [u8; READ_BUFFER_SIZE],
+    connected: bool,
+}
+
+impl SerialTransport {
+    /// Creates a transport for an opened port, see [`SerialConfig::open`].
+    pub fn new(stream: SerialStream) -> Self {
+        Self {
+            stream,
+            buffer: [0; READ_BUFFER_SIZE],
+            connected: false,
+        }
+    }
+
+    pub fn stream(&self) -> &SerialStream {
+        &self.stream
+    }
+
+    pub fn into_stream(self) -> SerialStream {
+        self.stream
+    }
+}
+
+impl Transport for SerialTransport {
+    async fn read(&mut self) -> io::Result<TransportEvent> {
+        if !self.connected {
+            self.connected = true;
+            return Ok(TransportEvent::Connected);
+        }
+        match self.stream.read(&mut self.buffer).await? {
+            0 => Ok(TransportEvent::Closed),
+            len => Ok(TransportEvent::Received(Bytes(self.buffer[..len].to_vec()))),
+        }
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.stream.write_all(bytes).await?;
+        self.stream.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        client::Client,
+        command::Command,
+        encoder::FrameEncoder,
+        frameparser::FrameParser,
+        protocol::{pgn, TbsPg},
+        transport::run_transport,
+    };
+    use tokio::sync::mpsc;
+    use tokio_serial::SerialPort;
+
+    #[tokio::test]
+    async fn test_serial_transport_on_pty() {
+        let (mut device, port) = SerialStream::pair().unwrap();
+        // Reopen the port by its path, as applications do, with the settings of the config.
+        let path = port.name().unwrap();
+        drop(port);
+        let config = SerialConfig::new(path, 9600)
+            .with_baud_rate(19200)
+            .with_parity(Parity::Even)
+            .with_stop_bits(StopBits::Two);
+        let transport = config.open().unwrap();
+        // Pseudo-terminals keep the baud rate and stop bits, but not the parity.
+        assert_eq!(transport.stream().baud_rate().unwrap(), 19200);
+        assert_eq!(transport.stream().stop_bits().unwrap(), StopBits::Two);
+
+        let (incoming_tx, incoming_rx) = mpsc::channel(5);
+        let (outgoing_tx, outgoing_rx) = mpsc::channel(5);
+        let (frames_tx, frames_rx) = mpsc::channel(5);
+        tokio::spawn(run_transport(transport, incoming_tx, outgoing_rx));
+        tokio::spawn(async move {
+            FrameParser::new()
+                .parse_frames(incoming_rx, frames_tx)
+                .await
+        });
+        let mut client = Client::new(frames_rx, outgoing_tx);
+
+        let command = Command::Request {
+            pgn: pgn::HEARTBEAT,
+            data_page: 1,
+        };
+        let expected = FrameEncoder::new().encode_frame(&command.to_frame(0xFD, 0x00));
+        let response = tokio::spawn(async move { client.send(0x00, &command).await });
+        let mut written = vec![0; expected.len()];
+        device.read_exact(&mut written).await.unwrap();
+        assert_eq!(written, expected);
+
+        let heartbeat = FrameEncoder::new().encode(0x00, 0xFF, pgn::HEARTBEAT, &[]);
+        device.write_all(&heartbeat).await.unwrap();
+        assert!(matches!(
+            response.await.unwrap().unwrap().body,
+            TbsPg::Heartbeat
+        ));
+    }
+}
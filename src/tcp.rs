@@ -0,0 +1,321 @@
+/*
+ * Copyright (c) 2024 Dominik Röttsches
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use crate::transport::{Transport, TransportEvent};
+use crate::types::Bytes;
+use std::{io, net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::broadcast,
+    time::{self, Instant},
+};
+use tracing::{debug, info, warn};
+
+const READ_BUFFER_SIZE: usize = 256;
+const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const DEFAULT_CLIENT_BUFFER: usize = 64;
+
+/// TcpTransport is a [`Transport`] over a TCP connection, for example to a serial-over-IP
+/// gateway.
+///
+/// It connects on the first read and reconnects after the connection is lost, reporting
+/// [`TransportEvent::Connected`] and [`TransportEvent::Disconnected`]. Failed attempts are
+/// retried after the reconnect delay. `read` never returns an error; connection failures are
+/// reported as `Disconnected` and retried.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use laad::{tcp::TcpTransport, transport::run_transport};
+/// use tokio::sync::mpsc;
+/// #[tokio::main]
+/// async fn main() {
+///   let (bytes_tx, bytes_rx) = mpsc::channel(5);
+///   let (outgoing_tx, outgoing_rx) = mpsc::channel(5);
+///   let transport = TcpTransport::new("192.168.1.10:4001");
+///   tokio::spawn(run_transport(transport, bytes_tx, outgoing_rx));
+/// }
+/// ```
+pub struct TcpTransport {
+    address: String,
+    reconnect_delay: Duration,
+    stream: Option<TcpStream>,
+    reconnect_at: Option<Instant>,
+    buffer: [u8; READ_BUFFER_SIZE],
+}
+
+impl TcpTransport {
+    /// Creates a transport for `address`, such as `"gateway.local:4001"`, without connecting.
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            reconnect_delay: DEFAULT_RECONNECT_DELAY,
+            stream: None,
+            reconnect_at: None,
+            buffer: [0; READ_BUFFER_SIZE],
+        }
+    }
+
+    /// Sets the delay before reconnecting, 1 s by default.
+    pub fn with_reconnect_delay(mut self, reconnect_delay: Duration) -> Self {
+        self.reconnect_delay = reconnect_delay;
+        self
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    fn disconnect(&mut self) {
+        self.stream = None;
+        self.reconnect_at = Some(Instant::now() + self.reconnect_delay);
+    }
+}
+
+impl Transport for TcpTransport {
+    async fn read(&mut self) -> io::Result<TransportEvent> {
+        let Some(stream) = self.stream.as_mut() else {
+            loop {
+                if let Some(reconnect_at) = self.reconnect_at {
+                    time::sleep_until(reconnect_at).await;
+                }
+                match TcpStream::connect(&self.address).await {
+                    Ok(stream) => {
+                        info!("Connected to {}", self.address);
+                        self.stream = Some(stream);
+                        self.reconnect_at = None;
+                        return Ok(TransportEvent::Connected);
+                    }
+                    Err(err) => {
+                        warn!("Failed to connect to {}: {}", self.address, err);
+                        self.reconnect_at = Some(Instant::now() + self.reconnect_delay);
+                    }
+                }
+            }
+        };
+        match stream.read(&mut self.buffer).await {
+            Ok(0) => {
+                info!("Connection to {} closed", self.address);
+                self.disconnect();
+                Ok(TransportEvent::Disconnected)
+            }
+            Ok(len) => Ok(TransportEvent::Received(Bytes(self.buffer[..len].to_vec()))),
+            Err(err) => {
+                warn!("Connection to {} failed: {}", self.address, err);
+                self.disconnect();
+                Ok(TransportEvent::Disconnected)
+            }
+        }
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match self.stream.as_mut() {
+            Some(stream) => stream.write_all(bytes).await,
+            None => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+}
+
+/// TcpServer re-broadcasts the raw bytes received from one source, such as a serial port, to all
+/// connected TCP clients.
+///
+/// Clients connect at any time and receive the bytes from then on, not aligned to frames. Bytes
+/// sent by clients are discarded. A client that does not keep up loses bytes, as a device would
+/// on a busy link, and the frame parser of the client resynchronizes.
+pub struct TcpServer {
+    listener: TcpListener,
+    client_buffer: usize,
+}
+
+impl TcpServer {
+    pub async fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self::new(TcpListener::bind(address).await?))
+    }
+
+    pub fn new(listener: TcpListener) -> Self {
+        Self {
+            listener,
+            client_buffer: DEFAULT_CLIENT_BUFFER,
+        }
+    }
+
+    /// Sets the number of chunks of bytes buffered for each client, 64 by default.
+    pub fn with_client_buffer(mut self, client_buffer: usize) -> Self {
+        self.client_buffer = client_buffer;
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts clients and sends them the bytes received from `source`, until the source is
+    /// closed or fails. Clients are disconnected when the source is closed.
+    pub async fn serve<T: Transport>(self, mut source: T) -> io::Result<()> {
+        let (sender, _) = broadcast::channel(self.client_buffer);
+        loop {
+            tokio::select! {
+                event = source.read() => match event? {
+                    TransportEvent::Received(bytes) => {
+                        // Without clients, the bytes are dropped.
+                        let _ = sender.send(bytes);
+                    }
+                    TransportEvent::Connected => info!("Source connected"),
+                    TransportEvent::Disconnected => warn!("Source disconnected"),
+                    TransportEvent::Closed => return Ok(()),
+                },
+                accepted = self.listener.accept() => match accepted {
+                    Ok((stream, address)) => {
+                        info!("Client {} connected", address);
+                        tokio::spawn(serve_client(stream, address, sender.subscribe()));
+                    }
+                    Err(err) => warn!("Failed to accept client: {}", err),
+                },
+            }
+        }
+    }
+}
+
+async fn serve_client(
+    mut stream: TcpStream,
+    address: SocketAddr,
+    mut receiver: broadcast::Receiver<Bytes>,
+) {
+    let (mut reader, mut writer) = stream.split();
+    let mut discarded = [0; READ_BUFFER_SIZE];
+    loop {
+        tokio::select! {
+            bytes = receiver.recv() => match bytes {
+                Ok(Bytes(bytes)) => {
+                    if let Err(err) = writer.write_all(&bytes).await {
+                        debug!("Failed to write to client {}: {}", address, err);
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(chunks)) => {
+                    warn!("Client {} lagging, skipped {} chunks", address, chunks);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            read = reader.read(&mut discarded) => match read {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            },
+        }
+    }
+    info!("Client {} disconnected", address);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        encoder::FrameEncoder, frameparser::FrameParser, protocol::pgn, transport::run_transport,
+    };
+    use tokio::sync::mpsc;
+
+    fn heartbeat() -> Vec<u8> {
+        FrameEncoder::new().encode(0x00, 0xFF, pgn::HEARTBEAT, &[])
+    }
+
+    #[tokio::test]
+    async fn test_tcp_transport_reconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut transport = TcpTransport::new(listener.local_addr().unwrap().to_string())
+            .with_reconnect_delay(Duration::from_millis(10));
+
+        let (event, accepted) = tokio::join!(transport.read(), listener.accept());
+        assert!(matches!(event.unwrap(), TransportEvent::Connected));
+        let (mut gateway, _) = accepted.unwrap();
+        assert!(transport.is_connected());
+
+        transport.write(&heartbeat()).await.unwrap();
+        let mut written = vec![0; heartbeat().len()];
+        gateway.read_exact(&mut written).await.unwrap();
+        assert_eq!(written, heartbeat());
+
+        gateway.write_all(&heartbeat()).await.unwrap();
+        match transport.read().await.unwrap() {
+            TransportEvent::Received(Bytes(bytes)) => assert_eq!(bytes, heartbeat()),
+            event => panic!("Unexpected event {:?}", event),
+        }
+
+        drop(gateway);
+        assert!(matches!(
+            transport.read().await.unwrap(),
+            TransportEvent::Disconnected
+        ));
+        assert!(!transport.is_connected());
+        assert_eq!(
+            transport.write(&heartbeat()).await.unwrap_err().kind(),
+            io::ErrorKind::NotConnected
+        );
+
+        let (event, accepted) = tokio::join!(transport.read(), listener.accept());
+        assert!(matches!(event.unwrap(), TransportEvent::Connected));
+        accepted.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tcp_server_broadcasts_to_clients() {
+        let source_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let source = TcpTransport::new(source_listener.local_addr().unwrap().to_string());
+        let server = TcpServer::bind("127.0.0.1:0").await.unwrap();
+        let address = server.local_addr().unwrap();
+        let server = tokio::spawn(server.serve(source));
+        let (mut device, _) = source_listener.accept().await.unwrap();
+
+        let mut clients = Vec::new();
+        for _ in 0..2 {
+            let (frames_tx, frames_rx) = mpsc::channel(5);
+            let (bytes_tx, bytes_rx) = mpsc::channel(5);
+            let (outgoing_tx, outgoing_rx) = mpsc::channel(5);
+            tokio::spawn(run_transport(
+                TcpTransport::new(address.to_string()),
+                bytes_tx,
+                outgoing_rx,
+            ));
+            tokio::spawn(async move { FrameParser::new().parse_frames(bytes_rx, frames_tx).await });
+            clients.push((frames_rx, outgoing_tx));
+        }
+
+        // Clients only receive bytes once they are accepted, so repeat until all received one.
+        let mut received = vec![false; clients.len()];
+        while received.contains(&false) {
+            let heartbeat = heartbeat();
+            let (first, second) = heartbeat.split_at(5);
+            device.write_all(first).await.unwrap();
+            device.write_all(second).await.unwrap();
+            for (index, (frames, _)) in clients.iter_mut().enumerate() {
+                if let Ok(Some(frame)) =
+                    time::timeout(Duration::from_millis(50), frames.recv()).await
+                {
+                    assert_eq!(frame.0, heartbeat.clone().into());
+                    received[index] = true;
+                }
+            }
+        }
+
+        server.abort();
+    }
+}
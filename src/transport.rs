@@ -0,0 +1,184 @@
+/*
+ * Copyright (c) 2024 Dominik Röttsches
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use crate::{encoder::FrameEncoder, types::Bytes, types::Frame};
+use std::{future::Future, io};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// Events of a [`Transport`], received bytes and changes of the connection.
+#[derive(Debug)]
+pub enum TransportEvent {
+    /// The transport connected to the link, or reconnected after
+    /// [`TransportEvent::Disconnected`].
+    Connected,
+    /// Bytes received from the link, in chunks as they arrive, not aligned to frames.
+    Received(Bytes),
+    /// The transport lost the connection. Transports that reconnect report
+    /// [`TransportEvent::Connected`] again.
+    Disconnected,
+    /// The transport is closed, no events follow.
+    Closed,
+}
+
+/// A link to TBS devices, such as a serial port, a BLE connection or a TCP connection, that
+/// receives bytes and sends encoded frames.
+///
+/// Use [`run_transport`] to connect a transport to a
+/// [`FrameParser`](crate::frameparser::FrameParser) and a [`Client`](crate::client::Client).
+pub trait Transport: Send {
+    /// Waits for the next event. Errors end the transport.
+    ///
+    /// Must be cancel safe: if the future is dropped before it completes, no received bytes may
+    /// be lost, as [`run_transport`] drops it to write while waiting.
+    fn read(&mut self) -> impl Future<Output = io::Result<TransportEvent>> + Send;
+
+    /// Writes bytes of encoded frames, see [`FrameEncoder`].
+    fn write(&mut self, bytes: &[u8]) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// Encodes and writes a frame.
+    fn write_frame(&mut self, frame: &Frame) -> impl Future<Output = io::Result<()>> + Send {
+        let bytes = FrameEncoder::new().encode_frame(frame);
+        async move { self.write(&bytes).await }
+    }
+}
+
+/// Runs a transport, sending received bytes to `incoming`, for example to
+/// [`FrameParser::parse_frames`](crate::frameparser::FrameParser::parse_frames), and writing
+/// bytes from `outgoing`, for example from a [`Client`](crate::client::Client).
+///
+/// Returns when the transport is closed or `incoming` is closed, or with the error of the
+/// transport. Errors writing are logged, as the link may recover from them.
+pub async fn run_transport<T: Transport>(
+    mut transport: T,
+    incoming: mpsc::Sender<Bytes>,
+    mut outgoing: mpsc::Receiver<Bytes>,
+) -> io::Result<()> {
+    let mut outgoing_open = true;
+    loop {
+        tokio::select! {
+            event = transport.read() => match event? {
+                TransportEvent::Received(bytes) => {
+                    if incoming.send(bytes).await.is_err() {
+                        return Ok(());
+                    }
+                }
+                TransportEvent::Connected => info!("Transport connected"),
+                TransportEvent::Disconnected => warn!("Transport disconnected"),
+                TransportEvent::Closed => return Ok(()),
+            },
+            bytes = outgoing.recv(), if outgoing_open => match bytes {
+                Some(Bytes(bytes)) => {
+                    if let Err(err) = transport.write(&bytes).await {
+                        warn!("Failed to write to transport: {}", err);
+                    }
+                }
+                None => outgoing_open = false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        client::Client,
+        command::Command,
+        frameparser::FrameParser,
+        protocol::{pgn, TbsPg},
+    };
+
+    /// A transport replaying events, recording written bytes.
+    struct ReplayTransport {
+        events: mpsc::Receiver<TransportEvent>,
+        written: mpsc::Sender<Vec<u8>>,
+    }
+
+    impl Transport for ReplayTransport {
+        async fn read(&mut self) -> io::Result<TransportEvent> {
+            Ok(self.events.recv().await.unwrap_or(TransportEvent::Closed))
+        }
+
+        async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+            self.written
+                .send(bytes.to_vec())
+                .await
+                .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_transport() {
+        let (events_tx, events) = mpsc::channel(5);
+        let (written, mut written_rx) = mpsc::channel(5);
+        let (incoming_tx, incoming_rx) = mpsc::channel(5);
+        let (outgoing_tx, outgoing_rx) = mpsc::channel(5);
+        let (frames_tx, frames_rx) = mpsc::channel(5);
+        let transport = tokio::spawn(run_transport(
+            ReplayTransport { events, written },
+            incoming_tx,
+            outgoing_rx,
+        ));
+        tokio::spawn(async move {
+            FrameParser::new()
+                .parse_frames(incoming_rx, frames_tx)
+                .await
+        });
+        let mut client = Client::new(frames_rx, outgoing_tx);
+
+        events_tx.send(TransportEvent::Connected).await.unwrap();
+        let heartbeat = FrameEncoder::new().encode(0x00, 0xFF, pgn::HEARTBEAT, &[]);
+        let (first, second) = heartbeat.split_at(3);
+        for chunk in [first, second] {
+            events_tx
+                .send(TransportEvent::Received(Bytes(chunk.to_vec())))
+                .await
+                .unwrap();
+        }
+        assert!(matches!(
+            client.recv().await.unwrap().body,
+            TbsPg::Heartbeat
+        ));
+
+        let command = Command::Request {
+            pgn: pgn::HEARTBEAT,
+            data_page: 1,
+        };
+        let expected = FrameEncoder::new().encode_frame(&command.to_frame(0xFD, 0x00));
+        let response = tokio::spawn(async move { client.send(0x00, &command).await });
+        assert_eq!(written_rx.recv().await.unwrap(), expected);
+        events_tx.send(TransportEvent::Disconnected).await.unwrap();
+        events_tx.send(TransportEvent::Connected).await.unwrap();
+        events_tx
+            .send(TransportEvent::Received(Bytes(heartbeat)))
+            .await
+            .unwrap();
+        assert!(matches!(
+            response.await.unwrap().unwrap().body,
+            TbsPg::Heartbeat
+        ));
+
+        events_tx.send(TransportEvent::Closed).await.unwrap();
+        transport.await.unwrap().unwrap();
+    }
+}
@@ -23,13 +23,137 @@
 use std::fmt::{Display, Write};
 
 /// Incoming bytes received for example over serial, the BLE receiver, or a replay, see examples.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Bytes(pub Vec<u8>);
 
 /// A struct that represents a frame of bytes, with a start byte (0xAA) and an end byte (0x99), de-bytestffed.
 #[derive(Debug)]
 pub struct Frame(pub Box<[u8]>);
 
+/// The addressing information at the start of a frame, following the start byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameHeader {
+    /// Address of the device that sent the frame.
+    pub source: u8,
+    /// Address of the device the frame is sent to, 0xFF for broadcasts.
+    pub destination: u8,
+    pub pgn: u16,
+    /// Length of the payload as declared in the frame.
+    pub length: u8,
+}
+
+impl FrameHeader {
+    /// Address used as destination for broadcast frames.
+    pub const BROADCAST_ADDRESS: u8 = 0xFF;
+
+    pub fn is_broadcast(&self) -> bool {
+        self.destination == Self::BROADCAST_ADDRESS
+    }
+}
+
+/// A frame borrowed from a buffer, for example from the internal buffer of the
+/// [`FrameParser`](crate::frameparser::FrameParser), with the same layout as [`Frame`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRef<'a>(pub &'a [u8]);
+
+impl<'a> FrameRef<'a> {
+    /// Returns the header of the frame, or `None` if the frame is too short to contain one.
+    pub fn header(&self) -> Option<FrameHeader> {
+        if self.0.len() < Frame::MIN_LEN {
+            return None;
+        }
+        Some(FrameHeader {
+            source: self.0[1],
+            destination: self.0[2],
+            pgn: u16::from_le_bytes([self.0[3], self.0[4]]),
+            length: self.0[5],
+        })
+    }
+
+    /// Returns the bytes between the header and the checksum.
+    pub fn payload(&self) -> Option<&'a [u8]> {
+        if self.0.len() < Frame::MIN_LEN {
+            return None;
+        }
+        Some(&self.0[6..self.0.len() - 2])
+    }
+
+    /// Copies the borrowed bytes into an owned [`Frame`].
+    pub fn to_frame(&self) -> Frame {
+        Frame(self.0.into())
+    }
+}
+
+impl Frame {
+    /// Minimum length of a frame: start byte, header, checksum and end byte.
+    pub const MIN_LEN: usize = 8;
+
+    /// Borrows the frame as a [`FrameRef`].
+    pub fn as_frame_ref(&self) -> FrameRef<'_> {
+        FrameRef(&self.0)
+    }
+
+    /// Returns the header of the frame, or `None` if the frame is too short to contain one.
+    pub fn header(&self) -> Option<FrameHeader> {
+        self.as_frame_ref().header()
+    }
+
+    /// Returns the source address, see [`FrameHeader::source`].
+    pub fn source(&self) -> Option<u8> {
+        self.header().map(|header| header.source)
+    }
+
+    /// Returns the destination address, see [`FrameHeader::destination`].
+    pub fn destination(&self) -> Option<u8> {
+        self.header().map(|header| header.destination)
+    }
+
+    /// Returns the PGN of the frame.
+    pub fn pgn(&self) -> Option<u16> {
+        self.header().map(|header| header.pgn)
+    }
+
+    /// Returns the bytes between the header and the checksum.
+    pub fn payload(&self) -> Option<&[u8]> {
+        self.as_frame_ref().payload()
+    }
+
+    /// Builds a frame from its source address, destination address, PGN and payload.
+    /// Adds the start byte, the length byte, the checksum and the end byte.
+    /// The frame is not bytestuffed, use [`FrameEncoder`](crate::encoder::FrameEncoder) to
+    /// get the bytes to send.
+    ///
+    /// # Panics
+    /// Panics if the payload is longer than 255 bytes.
+    pub fn build(source: u8, destination: u8, pgn: u16, payload: &[u8]) -> Frame {
+        let length = u8::try_from(payload.len()).expect("Payload too long for a frame");
+        let mut bytes = Vec::with_capacity(payload.len() + 8);
+        bytes.push(START_BYTE);
+        bytes.push(source);
+        bytes.push(destination);
+        bytes.extend_from_slice(&pgn.to_le_bytes());
+        bytes.push(length);
+        bytes.extend_from_slice(payload);
+        bytes.push(calculate_checksum(&bytes[1..]));
+        bytes.push(END_BYTE);
+        Frame(bytes.into_boxed_slice())
+    }
+}
+
+/// Start byte of a frame.
+pub(crate) const START_BYTE: u8 = 0xAA;
+/// End byte of a frame.
+pub(crate) const END_BYTE: u8 = 0x99;
+/// Escape byte for bytestuffing, the following byte is XORed with 0x20.
+pub(crate) const ESCAPE_BYTE: u8 = 0xA9;
+
+/// Calculates the checksum over the bytes between the start byte and the checksum byte of a frame.
+pub(crate) fn calculate_checksum(bytes: &[u8]) -> u8 {
+    let sum: u8 = bytes.iter().fold(0, |acc, &b| acc.wrapping_add(b));
+    sum.wrapping_neg()
+}
+
 impl Display for Frame {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let s = self
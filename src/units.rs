@@ -0,0 +1,245 @@
+/*
+ * Copyright (c) 2024 Dominik Röttsches
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Physical units of decoded quantities.
+//!
+//! Each unit keeps the integer value at the resolution it is sent with, for example hundredths of
+//! a volt, so that values are exact and comparable. Conversions to floating point are provided
+//! for calculations, `Display` prints the exact decimal value with its unit symbol.
+
+use std::fmt;
+use std::ops::Mul;
+
+macro_rules! unit {
+    (
+        $(#[$meta:meta])*
+        $name:ident($int:ty), $steps:literal per unit, $decimals:literal decimals, $symbol:literal,
+        $from_steps:ident, $steps_getter:ident
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+        pub struct $name($int);
+
+        impl $name {
+            /// Number of steps of the integer value per unit.
+            pub const STEPS_PER_UNIT: $int = $steps;
+
+            /// Creates the value from an integer number of steps, see [`Self::STEPS_PER_UNIT`].
+            pub const fn $from_steps(steps: $int) -> Self {
+                Self(steps)
+            }
+
+            /// Returns the integer number of steps, see [`Self::STEPS_PER_UNIT`].
+            pub const fn $steps_getter(&self) -> $int {
+                self.0
+            }
+
+            pub fn as_f32(&self) -> f32 {
+                self.0 as f32 / $steps as f32
+            }
+
+            pub fn as_f64(&self) -> f64 {
+                self.0 as f64 / $steps as f64
+            }
+        }
+
+        impl From<$name> for f32 {
+            fn from(value: $name) -> f32 {
+                value.as_f32()
+            }
+        }
+
+        impl From<$name> for f64 {
+            fn from(value: $name) -> f64 {
+                value.as_f64()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write_decimal(f, self.0 as i64, $steps, $decimals)?;
+                write!(f, " {}", $symbol)
+            }
+        }
+
+        /// Serialized as a number in the unit, for example `11.31` for 11.31 V.
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_f64(self.as_f64())
+            }
+        }
+
+        /// Deserialized from a number in the unit, rounded to the resolution of the unit.
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = f64::deserialize(deserializer)?;
+                let steps = (value * $steps as f64).round();
+                if !(<$int>::MIN as f64..=<$int>::MAX as f64).contains(&steps) {
+                    return Err(serde::de::Error::custom(format_args!(
+                        "{} out of range for {}",
+                        value,
+                        stringify!($name)
+                    )));
+                }
+                Ok(Self(steps as $int))
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}(", stringify!($name))?;
+                write_decimal(f, self.0 as i64, $steps, $decimals)?;
+                write!(f, ")")
+            }
+        }
+    };
+}
+
+/// Writes `steps / steps_per_unit` with `decimals` decimals, `steps_per_unit` must divide
+/// `10^decimals`.
+fn write_decimal(
+    f: &mut fmt::Formatter<'_>,
+    steps: i64,
+    steps_per_unit: i64,
+    decimals: u32,
+) -> fmt::Result {
+    let scale = 10i64.pow(decimals);
+    let scaled = steps * (scale / steps_per_unit);
+    let sign = if scaled < 0 { "-" } else { "" };
+    let scaled = scaled.unsigned_abs();
+    let scale = scale as u64;
+    if decimals == 0 {
+        write!(f, "{}{}", sign, scaled)
+    } else {
+        write!(
+            f,
+            "{}{}.{:0width$}",
+            sign,
+            scaled / scale,
+            scaled % scale,
+            width = decimals as usize
+        )
+    }
+}
+
+unit!(
+    /// Voltage, with a resolution of 0.01 V.
+    Volts(i32), 100 per unit, 2 decimals, "V", from_centivolts, centivolts
+);
+
+unit!(
+    /// Current, with a resolution of 0.01 A. Negative when discharging.
+    Amperes(i32), 100 per unit, 2 decimals, "A", from_centiamperes, centiamperes
+);
+
+unit!(
+    /// Power, with a resolution of 0.1 W. Negative when discharging.
+    Watts(i32), 10 per unit, 1 decimals, "W", from_deciwatts, deciwatts
+);
+
+unit!(
+    /// Charge, with a resolution of 0.01 Ah.
+    AmpHours(i32), 100 per unit, 2 decimals, "Ah", from_centiamp_hours, centiamp_hours
+);
+
+unit!(
+    /// Temperature, with a resolution of 0.5 °C.
+    Celsius(i16), 2 per unit, 1 decimals, "°C", from_half_degrees, half_degrees
+);
+
+unit!(
+    /// Percentage, with a resolution of 0.01 %.
+    Percent(u16), 100 per unit, 2 decimals, "%", from_hundredths, hundredths
+);
+
+impl Mul<Amperes> for Volts {
+    type Output = Watts;
+
+    /// Calculates the power, rounded to the resolution of [`Watts`].
+    fn mul(self, current: Amperes) -> Watts {
+        // Centivolts times centiamperes are 1/10000 W, rounded to 1/10 W.
+        let product = self.0 as i64 * current.0 as i64;
+        Watts(((product + product.signum() * 500) / 1000) as i32)
+    }
+}
+
+impl Mul<Volts> for Amperes {
+    type Output = Watts;
+
+    fn mul(self, voltage: Volts) -> Watts {
+        voltage * self
+    }
+}
+
+impl Celsius {
+    pub fn as_fahrenheit(&self) -> f32 {
+        self.as_f32() * 1.8 + 32.0
+    }
+
+    pub fn as_kelvin(&self) -> f32 {
+        self.as_f32() + 273.15
+    }
+}
+
+impl Percent {
+    /// Returns the percentage as a fraction, 1.0 being 100 %.
+    pub fn as_fraction(&self) -> f32 {
+        self.as_f32() / 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_is_exact() {
+        assert_eq!(Volts::from_centivolts(1131).to_string(), "11.31 V");
+        assert_eq!(Amperes::from_centiamperes(-6).to_string(), "-0.06 A");
+        assert_eq!(Watts::from_deciwatts(-6).to_string(), "-0.6 W");
+        assert_eq!(
+            AmpHours::from_centiamp_hours(-2780).to_string(),
+            "-27.80 Ah"
+        );
+        assert_eq!(Celsius::from_half_degrees(-81).to_string(), "-40.5 °C");
+        assert_eq!(Percent::from_hundredths(7300).to_string(), "73.00 %");
+        assert_eq!(format!("{:?}", Volts::from_centivolts(5)), "Volts(0.05)");
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert!((Volts::from_centivolts(1131).as_f64() - 11.31).abs() < 1e-9);
+        assert_eq!(f32::from(Celsius::from_half_degrees(41)), 20.5);
+        assert_eq!(Celsius::from_half_degrees(0).as_fahrenheit(), 32.0);
+        assert_eq!(Percent::from_hundredths(5000).as_fraction(), 0.5);
+        assert_eq!(
+            Volts::from_centivolts(1200) * Amperes::from_centiamperes(-250),
+            Watts::from_deciwatts(-300)
+        );
+        assert_eq!(
+            Amperes::from_centiamperes(6) * Volts::from_centivolts(1131),
+            Watts::from_deciwatts(7)
+        );
+    }
+}